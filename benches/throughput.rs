@@ -0,0 +1,60 @@
+//! Measures how fast responses are read from the local test server.
+//!
+//! Run with `cargo bench`.
+
+#[path = "../tests/common/mod.rs"]
+#[allow(dead_code)]
+mod common;
+
+use self::common::*;
+use std::io;
+use std::time::{Duration, Instant};
+
+const BODY_SIZE: usize = 64 * 1024 * 1024;
+const HEADER_COUNT: usize = 5000;
+const ROUNDS: u32 = 5;
+
+fn main() {
+    setup();
+    let content_length = url(&format!("/bytes/{}", BODY_SIZE));
+    let chunked = url(&format!("/chunked/{}", BODY_SIZE));
+    let many_headers = url(&format!("/headers/{}", HEADER_COUNT));
+
+    bench("send, Content-Length", BODY_SIZE, || {
+        let response = tinyget::get(&content_length).send().unwrap();
+        assert_eq!(response.as_bytes().len(), BODY_SIZE);
+    });
+    bench("send, chunked", BODY_SIZE, || {
+        let response = tinyget::get(&chunked).send().unwrap();
+        assert_eq!(response.as_bytes().len(), BODY_SIZE);
+    });
+    bench("send_lazy + io::copy, chunked", BODY_SIZE, || {
+        let mut response = tinyget::get(&chunked).send_lazy().unwrap();
+        let copied = io::copy(&mut response, &mut io::sink()).unwrap();
+        assert_eq!(copied as usize, BODY_SIZE);
+    });
+    bench("send_lazy + iterator, Content-Length", BODY_SIZE, || {
+        let response = tinyget::get(&content_length).send_lazy().unwrap();
+        assert_eq!(response.map(Result::unwrap).count(), BODY_SIZE);
+    });
+    bench("send, 5000 headers", 0, || {
+        let response = tinyget::get(&many_headers).send().unwrap();
+        assert!(response.headers.len() > HEADER_COUNT);
+    });
+}
+
+fn bench<F: FnMut()>(name: &str, bytes: usize, mut f: F) {
+    f(); // Warm up.
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed());
+    }
+    if bytes > 0 {
+        let throughput = bytes as f64 / best.as_secs_f64() / (1024.0 * 1024.0);
+        println!("{:<40} {:>10.2?} {:>10.1} MiB/s", name, best, throughput);
+    } else {
+        println!("{:<40} {:>10.2?}", name, best);
+    }
+}
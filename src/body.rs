@@ -0,0 +1,355 @@
+use crate::{Error, Headers, Multipart};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+#[cfg(target_os = "linux")]
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+type SharedReader = Arc<Mutex<Option<Box<dyn Read + Send>>>>;
+
+/// A stream the body of a request is written into.
+pub(crate) trait BodyWriter: Write {
+    /// Returns the plain TCP connection under this stream, if bytes
+    /// can be written into it directly after flushing this stream.
+    /// Only used for sendfile(2), so other platforms always copy the
+    /// body through the stream.
+    #[cfg(target_os = "linux")]
+    fn plain_socket(&self) -> Option<&TcpStream> {
+        None
+    }
+}
+
+/// The body of a [`Request`](struct.Request.html), or of a part of a
+/// [`Multipart`](struct.Multipart.html) body.
+#[derive(Clone)]
+pub(crate) enum Body {
+    /// A body that is already in memory. The body of a request is
+    /// sent along with the head.
+    Bytes(Vec<u8>),
+    /// A body that is streamed from a reader after the head. The
+    /// reader is shared between clones of the request, and taken out
+    /// when the body is sent, so it can only be sent once.
+    Reader {
+        reader: SharedReader,
+        length: Option<u64>,
+    },
+    /// A body that is sent from a file after the head, from its
+    /// start. The length is `None` if the file's metadata couldn't be
+    /// read.
+    File {
+        file: Arc<File>,
+        length: Option<u64>,
+    },
+    /// A `multipart/form-data` body.
+    Multipart(Multipart),
+}
+
+impl Body {
+    pub(crate) fn from_reader<R: Read + Send + 'static>(reader: R, length: Option<u64>) -> Body {
+        Body::Reader {
+            reader: Arc::new(Mutex::new(Some(Box::new(reader)))),
+            length,
+        }
+    }
+
+    pub(crate) fn from_file(file: File) -> Body {
+        let length = file.metadata().ok().map(|metadata| metadata.len());
+        Body::File {
+            file: Arc::new(file),
+            length,
+        }
+    }
+
+    /// Returns the length of the body, if it is known before sending
+    /// it.
+    pub(crate) fn len(&self) -> Option<u64> {
+        match self {
+            Body::Bytes(bytes) => Some(bytes.len() as u64),
+            Body::Reader { length, .. } | Body::File { length, .. } => *length,
+            Body::Multipart(multipart) => multipart.len(),
+        }
+    }
+
+    /// Writes the body into `stream`, unless it was already written
+    /// along with the head. Bodies of unknown length are written with
+    /// the chunked transfer coding, followed by `trailers`.
+    pub(crate) fn write_streamed<W: BodyWriter>(
+        &self,
+        stream: &mut W,
+        trailers: &Headers,
+    ) -> Result<(), Error> {
+        match self {
+            Body::Bytes(_) => Ok(()),
+            body if body.len().is_some() => body.write_contents(stream),
+            body => {
+                let mut chunked = ChunkedWriter { inner: stream };
+                body.write_contents(&mut chunked)?;
+                chunked.finish(trailers)
+            }
+        }
+    }
+
+    /// Writes the body into `stream` compressed with `coding`, with
+    /// the chunked transfer coding, followed by `trailers`, as the
+    /// compressed length is not known in advance.
+    #[cfg(feature = "compression")]
+    pub(crate) fn write_compressed<W: BodyWriter>(
+        &self,
+        stream: &mut W,
+        trailers: &Headers,
+        coding: crate::Coding,
+    ) -> Result<(), Error> {
+        let chunked = ChunkedWriter { inner: stream };
+        crate::compression::compress(coding, chunked, self)?.finish(trailers)
+    }
+
+    /// Writes the contents of the body into `stream`, without any
+    /// framing.
+    pub(crate) fn write_contents<W: BodyWriter>(&self, stream: &mut W) -> Result<(), Error> {
+        match self {
+            Body::Bytes(bytes) => {
+                stream.write_all(bytes)?;
+                Ok(())
+            }
+            Body::Reader { reader, length } => {
+                let mut reader = reader
+                    .lock()
+                    .map_err(|_| Error::Other("body reader mutex poisoned"))?
+                    .take()
+                    .ok_or(Error::BodyAlreadySent)?;
+                match length {
+                    Some(length) => write_exact(reader, *length, stream),
+                    None => {
+                        io::copy(&mut reader, stream)?;
+                        Ok(())
+                    }
+                }
+            }
+            Body::File { file, length } => {
+                // Files are sent from the start, so that the same
+                // request can be sent again, eg. when redirected.
+                let mut file = &**file;
+                match length {
+                    Some(length) => {
+                        #[cfg(target_os = "linux")]
+                        {
+                            stream.flush()?;
+                            if let Some(socket) = stream.plain_socket() {
+                                if sendfile(socket, file, *length)? {
+                                    return Ok(());
+                                }
+                            }
+                        }
+                        file.seek(SeekFrom::Start(0))?;
+                        write_exact(file, *length, stream)
+                    }
+                    None => {
+                        file.seek(SeekFrom::Start(0))?;
+                        io::copy(&mut file, stream)?;
+                        Ok(())
+                    }
+                }
+            }
+            Body::Multipart(multipart) => multipart.write_contents(stream),
+        }
+    }
+}
+
+/// Writes exactly `length` bytes from `reader` into `stream`.
+fn write_exact<R: Read, W: Write>(reader: R, length: u64, stream: &mut W) -> Result<(), Error> {
+    let copied = io::copy(&mut reader.take(length), stream)?;
+    if copied < length {
+        return Err(Error::IoError(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "the request body ended before its given length",
+        )));
+    }
+    Ok(())
+}
+
+/// Writes everything written into it as chunks of the chunked
+/// transfer coding.
+struct ChunkedWriter<'a, W: Write> {
+    inner: &'a mut W,
+}
+
+impl<W: Write> ChunkedWriter<'_, W> {
+    /// Writes the last chunk, followed by `trailers`.
+    fn finish(self, trailers: &Headers) -> Result<(), Error> {
+        self.inner.write_all(b"0\r\n")?;
+        for (name, value) in trailers {
+            write!(self.inner, "{}: {}\r\n", name, value)?;
+        }
+        self.inner.write_all(b"\r\n")?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for ChunkedWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // An empty chunk would end the body.
+        if !buf.is_empty() {
+            write!(self.inner, "{:x}\r\n", buf.len())?;
+            self.inner.write_all(buf)?;
+            self.inner.write_all(b"\r\n")?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> BodyWriter for ChunkedWriter<'_, W> {}
+
+/// Sends the first `length` bytes of `file` into `socket` with
+/// sendfile(2), without copying them through user space. Returns
+/// `false` if sendfile can't be used for these files, before anything
+/// was sent.
+#[cfg(target_os = "linux")]
+fn sendfile(socket: &TcpStream, file: &File, length: u64) -> Result<bool, Error> {
+    use std::os::unix::io::AsRawFd;
+
+    // Linux sends at most this many bytes per call.
+    const MAX_COUNT: u64 = 0x7fff_f000;
+
+    let mut offset: libc::off_t = 0;
+    let mut sent = 0;
+    while sent < length {
+        let count = (length - sent).min(MAX_COUNT) as usize;
+        // SAFETY: both file descriptors are open for the duration of
+        // the call, and `offset` is a valid pointer to an off_t.
+        let result =
+            unsafe { libc::sendfile(socket.as_raw_fd(), file.as_raw_fd(), &mut offset, count) };
+        if result < 0 {
+            let err = io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::EINTR) => continue,
+                Some(libc::EINVAL) | Some(libc::ENOSYS) if sent == 0 => return Ok(false),
+                _ => return Err(Error::IoError(err)),
+            }
+        } else if result == 0 {
+            return Err(Error::IoError(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the request body ended before its given length",
+            )));
+        }
+        sent += result as u64;
+    }
+    Ok(true)
+}
+
+impl Default for Body {
+    fn default() -> Body {
+        Body::Bytes(Vec::new())
+    }
+}
+
+impl PartialEq for Body {
+    fn eq(&self, other: &Body) -> bool {
+        match (self, other) {
+            (Body::Bytes(a), Body::Bytes(b)) => a == b,
+            (
+                Body::Reader { reader, length },
+                Body::Reader {
+                    reader: other_reader,
+                    length: other_length,
+                },
+            ) => Arc::ptr_eq(reader, other_reader) && length == other_length,
+            (
+                Body::File { file, length },
+                Body::File {
+                    file: other_file,
+                    length: other_length,
+                },
+            ) => Arc::ptr_eq(file, other_file) && length == other_length,
+            (Body::Multipart(a), Body::Multipart(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Body::Bytes(bytes) => f.debug_tuple("Bytes").field(bytes).finish(),
+            Body::Reader { length, .. } => {
+                f.debug_struct("Reader").field("length", length).finish()
+            }
+            Body::File { file, length } => f
+                .debug_struct("File")
+                .field("file", file)
+                .field("length", length)
+                .finish(),
+            Body::Multipart(multipart) => f.debug_tuple("Multipart").field(multipart).finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Body, BodyWriter};
+    use crate::{Error, Headers};
+
+    impl BodyWriter for Vec<u8> {}
+
+    #[test]
+    fn writes_unknown_length_as_chunks() {
+        let body = Body::from_reader(&b"Wikipedia"[..], None);
+        let trailers: Headers = vec![("Expires", "never")].into_iter().collect();
+        let mut written = Vec::new();
+        body.write_streamed(&mut written, &trailers).unwrap();
+        assert_eq!(written, b"9\r\nWikipedia\r\n0\r\nExpires: never\r\n\r\n");
+    }
+
+    #[test]
+    fn writes_known_length_as_is() {
+        let body = Body::from_reader(&b"Wikipedia"[..], Some(4));
+        let mut written = Vec::new();
+        body.write_streamed(&mut written, &Headers::new()).unwrap();
+        assert_eq!(written, b"Wiki");
+
+        let short = Body::from_reader(&b"Wiki"[..], Some(9));
+        let result = short.write_streamed(&mut Vec::new(), &Headers::new());
+        assert!(matches!(result, Err(Error::IoError(_))));
+    }
+
+    #[test]
+    fn writes_files_from_the_start() {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let path =
+            std::env::temp_dir().join(format!("tinyget-body-file-test-{}", std::process::id()));
+        let mut file = std::fs::File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"Wikipedia").unwrap();
+        file.seek(SeekFrom::Start(4)).unwrap();
+        let body = Body::from_file(file);
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(body.len(), Some(9));
+        for _ in 0..2 {
+            let mut written = Vec::new();
+            body.write_streamed(&mut written, &Headers::new()).unwrap();
+            assert_eq!(written, b"Wikipedia");
+        }
+    }
+
+    #[test]
+    fn can_only_be_sent_once() {
+        let body = Body::from_reader(&b"once"[..], None);
+        let clone = body.clone();
+        assert_eq!(body, clone);
+        body.write_streamed(&mut Vec::new(), &Headers::new())
+            .unwrap();
+        let result = clone.write_streamed(&mut Vec::new(), &Headers::new());
+        assert!(matches!(result, Err(Error::BodyAlreadySent)));
+    }
+}
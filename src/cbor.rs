@@ -0,0 +1,80 @@
+use crate::Error;
+use ciborium::{de, ser};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{self, Read};
+
+/// The `Content-Type` of CBOR request bodies.
+pub(crate) const CONTENT_TYPE: &str = "application/cbor";
+
+/// Returns whether `media_type`, in lowercase and without parameters,
+/// is CBOR, including structured syntax suffixes like
+/// `application/senml+cbor`.
+pub(crate) fn accepts(media_type: &str) -> bool {
+    media_type == CONTENT_TYPE
+        || (media_type.starts_with("application/") && media_type.ends_with("+cbor"))
+}
+
+pub(crate) fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).map_err(|error| match error {
+        ser::Error::Io(err) => Error::IoError(err),
+        ser::Error::Value(message) => Error::Cbor {
+            message,
+            offset: None,
+        },
+    })?;
+    Ok(bytes)
+}
+
+pub(crate) fn from_reader<R: Read, T: DeserializeOwned>(reader: R) -> Result<T, Error> {
+    ciborium::from_reader(reader).map_err(|error| match error {
+        // The errors of the reader, eg. a ResponseLazy. Running out of
+        // bytes means that the body was cut short.
+        de::Error::Io(err) if err.kind() != io::ErrorKind::UnexpectedEof => Error::from(err),
+        de::Error::Io(_) => Error::Cbor {
+            message: "unexpected end of the body".to_string(),
+            offset: None,
+        },
+        de::Error::Syntax(offset) => Error::Cbor {
+            message: "syntax error".to_string(),
+            offset: Some(offset),
+        },
+        de::Error::Semantic(offset, message) => Error::Cbor { message, offset },
+        de::Error::RecursionLimitExceeded => Error::Cbor {
+            message: "recursion limit exceeded".to_string(),
+            offset: None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accepts, from_reader, to_vec};
+    use crate::Error;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn round_trips() {
+        let map = BTreeMap::from([("a".to_string(), 1)]);
+        let bytes = to_vec(&map).unwrap();
+        assert_eq!(bytes, [0xa1, 0x61, b'a', 0x01]);
+        let decoded: BTreeMap<String, i32> = from_reader(&bytes[..]).unwrap();
+        assert_eq!(decoded, map);
+    }
+
+    #[test]
+    fn reports_truncated_and_invalid_bodies() {
+        let result: Result<BTreeMap<String, i32>, Error> = from_reader(&[0xa1, 0x61][..]);
+        assert!(matches!(result, Err(Error::Cbor { offset: None, .. })));
+        let result: Result<String, Error> = from_reader(&[0x01][..]);
+        assert!(matches!(result, Err(Error::Cbor { .. })));
+    }
+
+    #[test]
+    fn accepts_suffixes() {
+        assert!(accepts("application/cbor"));
+        assert!(accepts("application/senml+cbor"));
+        assert!(!accepts("application/json"));
+    }
+}
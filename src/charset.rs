@@ -0,0 +1,350 @@
+use crate::{ContentType, Error};
+use std::borrow::Cow;
+use std::str;
+
+/// How many bytes of an HTML body are searched for a `<meta>` charset
+/// declaration, like browsers do.
+const META_SNIFF_LEN: usize = 1024;
+
+/// A character encoding that response bodies can be decoded from.
+/// Without the `charset` feature, only the Unicode encodings are
+/// supported.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Charset {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    #[cfg(feature = "charset")]
+    Legacy(&'static encoding_rs::Encoding),
+}
+
+impl Charset {
+    /// Looks up the encoding called `label`, with the labels and
+    /// aliases of the WHATWG Encoding Standard.
+    fn for_label(label: &str) -> Result<Charset, Error> {
+        #[cfg(feature = "charset")]
+        {
+            use encoding_rs::{UTF_16BE, UTF_16LE, UTF_8};
+
+            match encoding_rs::Encoding::for_label(label.as_bytes()) {
+                Some(encoding) if encoding == UTF_8 => Ok(Charset::Utf8),
+                Some(encoding) if encoding == UTF_16LE => Ok(Charset::Utf16Le),
+                Some(encoding) if encoding == UTF_16BE => Ok(Charset::Utf16Be),
+                Some(encoding) => Ok(Charset::Legacy(encoding)),
+                None => Err(Error::UnsupportedCharset(label.to_string())),
+            }
+        }
+        #[cfg(not(feature = "charset"))]
+        {
+            match label.trim().to_ascii_lowercase().as_str() {
+                "utf-8" | "utf8" | "unicode-1-1-utf-8" => Ok(Charset::Utf8),
+                "utf-16" | "utf-16le" => Ok(Charset::Utf16Le),
+                "utf-16be" => Ok(Charset::Utf16Be),
+                _ => Err(Error::UnsupportedCharset(label.to_string())),
+            }
+        }
+    }
+
+    /// Returns the charset of the byte order mark `body` starts with,
+    /// and the length of the mark.
+    fn from_bom(body: &[u8]) -> Option<(Charset, usize)> {
+        match body {
+            [0xEF, 0xBB, 0xBF, ..] => Some((Charset::Utf8, 3)),
+            [0xFF, 0xFE, ..] => Some((Charset::Utf16Le, 2)),
+            [0xFE, 0xFF, ..] => Some((Charset::Utf16Be, 2)),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Charset::Utf8 => "UTF-8",
+            Charset::Utf16Le => "UTF-16LE",
+            Charset::Utf16Be => "UTF-16BE",
+            #[cfg(feature = "charset")]
+            Charset::Legacy(encoding) => encoding.name(),
+        }
+    }
+
+    /// Decodes `bytes`, which don't start with a byte order mark.
+    /// Malformed bytes are an error rather than replaced, so that no
+    /// text is lost silently.
+    fn decode(self, bytes: &[u8]) -> Result<Cow<'_, str>, Error> {
+        match self {
+            Charset::Utf8 => match str::from_utf8(bytes) {
+                Ok(text) => Ok(Cow::Borrowed(text)),
+                Err(err) => Err(Error::InvalidUtf8InBody(err)),
+            },
+            Charset::Utf16Le | Charset::Utf16Be => {
+                if !bytes.len().is_multiple_of(2) {
+                    return Err(Error::InvalidTextInBody(self.name()));
+                }
+                let units = bytes.chunks_exact(2).map(|pair| match self {
+                    Charset::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                });
+                char::decode_utf16(units)
+                    .collect::<Result<String, _>>()
+                    .map(Cow::Owned)
+                    .map_err(|_| Error::InvalidTextInBody(self.name()))
+            }
+            #[cfg(feature = "charset")]
+            Charset::Legacy(encoding) => encoding
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .ok_or(Error::InvalidTextInBody(encoding.name())),
+        }
+    }
+}
+
+/// Decodes `body` into text, with the charset of its byte order mark,
+/// the `charset` parameter of `content_type`, or the `<meta>` charset
+/// declaration of an HTML body, in that order of precedence. Bodies
+/// without any of them are decoded as UTF-8.
+pub(crate) fn decode<'a>(
+    body: &'a [u8],
+    content_type: Option<&ContentType>,
+) -> Result<Cow<'a, str>, Error> {
+    if let Some((charset, bom_len)) = Charset::from_bom(body) {
+        return charset.decode(&body[bom_len..]);
+    }
+    let label = content_type.and_then(ContentType::charset);
+    let charset = match label {
+        Some(label) => Charset::for_label(label)?,
+        None if content_type.is_some_and(is_html) => match sniff_meta(body) {
+            Some(label) => match Charset::for_label(&label)? {
+                // A declaration that can be read as ASCII can't be
+                // UTF-16, so browsers take it to mean UTF-8.
+                Charset::Utf16Le | Charset::Utf16Be => Charset::Utf8,
+                charset => charset,
+            },
+            None => Charset::Utf8,
+        },
+        None => Charset::Utf8,
+    };
+    charset.decode(body)
+}
+
+/// Decodes `body` with the charset called `label`, skipping a byte
+/// order mark of that charset.
+pub(crate) fn decode_with_label<'a>(body: &'a [u8], label: &str) -> Result<Cow<'a, str>, Error> {
+    let charset = Charset::for_label(label)?;
+    match Charset::from_bom(body) {
+        Some((bom_charset, bom_len)) if bom_charset == charset => charset.decode(&body[bom_len..]),
+        _ => charset.decode(body),
+    }
+}
+
+fn is_html(content_type: &ContentType) -> bool {
+    matches!(
+        content_type.essence(),
+        "text/html" | "application/xhtml+xml"
+    )
+}
+
+/// Looks for a `<meta charset="...">` or
+/// `<meta http-equiv="Content-Type" content="...; charset=...">` tag
+/// at the start of an HTML body, and returns the charset it declares.
+fn sniff_meta(body: &[u8]) -> Option<String> {
+    let head = &body[..body.len().min(META_SNIFF_LEN)];
+    let mut position = 0;
+    while position < head.len() {
+        let rest = &head[position..];
+        if rest.starts_with(b"<!--") {
+            position += find(rest, b"-->").map_or(head.len(), |end| end + 3);
+        } else if starts_with_ignore_case(rest, b"<meta")
+            && rest
+                .get(5)
+                .is_some_and(|&byte| byte.is_ascii_whitespace() || byte == b'/')
+        {
+            let (charset, length) = meta_charset(&rest[5..]);
+            if charset.is_some() {
+                return charset;
+            }
+            position += 5 + length;
+        } else {
+            position += 1;
+        }
+    }
+    None
+}
+
+/// Parses the attributes of a `<meta>` tag, and returns the charset
+/// it declares, and the length of the attributes.
+fn meta_charset(tag: &[u8]) -> (Option<String>, usize) {
+    let mut charset = None;
+    let mut http_equiv_content_type = false;
+    let mut content_charset = None;
+    let mut position = 0;
+    let skip = |position: &mut usize, skipped: fn(&u8) -> bool| {
+        while tag.get(*position).is_some_and(skipped) {
+            *position += 1;
+        }
+    };
+    loop {
+        skip(&mut position, |byte| {
+            byte.is_ascii_whitespace() || *byte == b'/'
+        });
+        if tag.get(position).is_none_or(|&byte| byte == b'>') {
+            break;
+        }
+        let start = position;
+        skip(&mut position, |byte| {
+            !byte.is_ascii_whitespace() && !matches!(byte, b'=' | b'>' | b'/')
+        });
+        let name = String::from_utf8_lossy(&tag[start..position]).to_ascii_lowercase();
+        skip(&mut position, u8::is_ascii_whitespace);
+        let mut value = Cow::Borrowed("");
+        if tag.get(position) == Some(&b'=') {
+            position += 1;
+            skip(&mut position, u8::is_ascii_whitespace);
+            let start;
+            match tag.get(position) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    start = position + 1;
+                    position = start + find(&tag[start..], &[quote]).unwrap_or(tag.len() - start);
+                    value = String::from_utf8_lossy(&tag[start..position]);
+                    position += 1;
+                }
+                _ => {
+                    start = position;
+                    skip(&mut position, |byte| {
+                        !byte.is_ascii_whitespace() && *byte != b'>'
+                    });
+                    value = String::from_utf8_lossy(&tag[start..position]);
+                }
+            }
+        }
+        match name.as_str() {
+            "charset" if charset.is_none() => charset = Some(value.trim().to_string()),
+            "http-equiv" => http_equiv_content_type = value.eq_ignore_ascii_case("content-type"),
+            "content" if content_charset.is_none() => {
+                content_charset = ContentType::parse(&value)
+                    .and_then(|content_type| Some(content_type.charset()?.trim().to_string()))
+            }
+            _ => {}
+        }
+    }
+    let length = position.min(tag.len());
+    match charset {
+        Some(charset) => (Some(charset), length),
+        None if http_equiv_content_type => (content_charset, length),
+        None => (None, length),
+    }
+}
+
+fn starts_with_ignore_case(haystack: &[u8], prefix: &[u8]) -> bool {
+    haystack.len() >= prefix.len() && haystack[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_with_label, sniff_meta};
+    use crate::{ContentType, Error};
+    use std::borrow::Cow;
+
+    fn decode<'a>(body: &'a [u8], content_type: Option<&str>) -> Result<Cow<'a, str>, Error> {
+        super::decode(body, content_type.and_then(ContentType::parse).as_ref())
+    }
+
+    #[test]
+    fn falls_back_to_utf8() {
+        let text = decode("héllo".as_bytes(), None).unwrap();
+        assert!(matches!(text, Cow::Borrowed("héllo")));
+        let text = decode("héllo".as_bytes(), Some("text/plain")).unwrap();
+        assert_eq!(text, "héllo");
+        assert!(matches!(
+            decode(b"h\xe9llo", None),
+            Err(Error::InvalidUtf8InBody(_))
+        ));
+    }
+
+    #[test]
+    fn prefers_the_bom() {
+        let body = b"\xff\xfeh\0i\0";
+        assert_eq!(
+            decode(body, Some("text/plain; charset=utf-8")).unwrap(),
+            "hi"
+        );
+        assert_eq!(decode(b"\xef\xbb\xbfhi", None).unwrap(), "hi");
+        assert_eq!(decode(b"\xfe\xff\0h\0i", None).unwrap(), "hi");
+        assert!(matches!(
+            decode(b"\xff\xfeh", None),
+            Err(Error::InvalidTextInBody("UTF-16LE"))
+        ));
+    }
+
+    #[test]
+    fn reads_the_charset_parameter() {
+        let body = b"h\0i\0";
+        assert_eq!(
+            decode(body, Some("text/plain; charset=\"UTF-16\"")).unwrap(),
+            "hi"
+        );
+        assert!(matches!(
+            decode(body, Some("text/plain; charset=klingon")),
+            Err(Error::UnsupportedCharset(label)) if label == "klingon"
+        ));
+    }
+
+    #[test]
+    fn sniffs_html_meta_tags() {
+        assert_eq!(
+            sniff_meta(b"<!doctype html><META Charset='utf-8'>").as_deref(),
+            Some("utf-8")
+        );
+        assert_eq!(
+            sniff_meta(
+                b"<meta name=viewport content=\"width=device-width\">\
+                  <meta http-equiv=Content-Type content=\"text/html; charset=utf-8\">"
+            )
+            .as_deref(),
+            Some("utf-8")
+        );
+        assert_eq!(sniff_meta(b"<!-- <meta charset=utf-8> --><p>"), None);
+        assert_eq!(sniff_meta(b"<meta content=\"charset=utf-8\">"), None);
+        assert_eq!(sniff_meta(b"<metadata charset=utf-8>"), None);
+
+        // Only HTML bodies are sniffed, and UTF-16 means UTF-8.
+        let body = b"<meta charset=utf-16><p>hi";
+        assert_eq!(
+            decode(body, Some("text/html")).unwrap(),
+            "<meta charset=utf-16><p>hi"
+        );
+        let body = b"<meta charset=klingon>";
+        assert!(decode(body, Some("text/html")).is_err());
+        assert!(decode(body, Some("text/plain")).is_ok());
+    }
+
+    #[test]
+    fn decodes_with_an_overriding_label() {
+        assert_eq!(decode_with_label(b"\xef\xbb\xbfhi", "utf-8").unwrap(), "hi");
+        assert_eq!(decode_with_label(b"h\0i\0", "utf-16le").unwrap(), "hi");
+    }
+
+    #[test]
+    #[cfg(feature = "charset")]
+    fn decodes_legacy_charsets() {
+        let text = decode(
+            b"caf\xe9 \x93quoted\x94",
+            Some("text/html; charset=iso-8859-1"),
+        )
+        .unwrap();
+        assert_eq!(text, "café \u{201c}quoted\u{201d}");
+        let text = decode(
+            b"<meta charset=shift_jis>\x93\xfa\x96\x7b",
+            Some("text/html"),
+        )
+        .unwrap();
+        assert_eq!(text, "<meta charset=shift_jis>日本");
+        assert!(matches!(
+            decode_with_label(b"\x82", "shift_jis"),
+            Err(Error::InvalidTextInBody("Shift_JIS"))
+        ));
+    }
+}
@@ -0,0 +1,462 @@
+use crate::body::{Body, BodyWriter};
+use crate::{Error, Headers};
+use flate2::write::{DeflateDecoder, GzEncoder, MultiGzDecoder, ZlibDecoder, ZlibEncoder};
+use flate2::Compression;
+use std::io::{self, Write};
+use std::mem;
+
+/// The value of the `Accept-Encoding` header sent with requests.
+pub(crate) const ACCEPT_ENCODING: &str = "gzip, deflate";
+
+/// A content coding request bodies can be compressed with. Set with
+/// [`Request::with_body_compression`](struct.Request.html#method.with_body_compression).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Coding {
+    /// `gzip`, the most widely supported coding.
+    Gzip,
+    /// `deflate`, ie. zlib data (RFC 1950).
+    Deflate,
+    /// `zstd` (RFC 8878), which compresses faster and better than
+    /// `gzip`, but is less widely supported. Requires the `zstd`
+    /// feature.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Coding {
+    /// Returns the name of the coding, for the `Content-Encoding`
+    /// header.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Coding::Gzip => "gzip",
+            Coding::Deflate => "deflate",
+            #[cfg(feature = "zstd")]
+            Coding::Zstd => "zstd",
+        }
+    }
+}
+
+/// Writes the contents of `body` into `stream`, compressed with
+/// `coding`, and returns the stream after the compressed data has been
+/// finished.
+pub(crate) fn compress<W: Write>(coding: Coding, stream: W, body: &Body) -> Result<W, Error> {
+    match coding {
+        Coding::Gzip => {
+            let mut encoder = GzEncoder::new(stream, Compression::default());
+            body.write_contents(&mut encoder)?;
+            Ok(encoder.finish()?)
+        }
+        Coding::Deflate => {
+            let mut encoder = ZlibEncoder::new(stream, Compression::default());
+            body.write_contents(&mut encoder)?;
+            Ok(encoder.finish()?)
+        }
+        #[cfg(feature = "zstd")]
+        Coding::Zstd => {
+            let mut encoder = zstd::stream::write::Encoder::new(stream, 0)?;
+            body.write_contents(&mut encoder)?;
+            Ok(encoder.finish()?)
+        }
+    }
+}
+
+impl<W: Write> BodyWriter for GzEncoder<W> {}
+
+impl<W: Write> BodyWriter for ZlibEncoder<W> {}
+
+#[cfg(feature = "zstd")]
+impl<W: Write> BodyWriter for zstd::stream::write::Encoder<'_, W> {}
+
+/// How large a body may always decompress to, regardless of the
+/// ratio, so that small but very compressible bodies are accepted.
+const MIN_RATIO_LIMIT: usize = 1024 * 1024;
+
+/// Decodes the content codings of a response body as it is read.
+///
+/// The encoded bytes are written through a stack of decoders, one for
+/// each coding, into a [`Sink`] the decoded bytes are read from. The
+/// sink refuses to grow past the decompression ratio limit, which
+/// stops the decoders in the middle of a write, instead of after a
+/// small input has already expanded into gigabytes.
+pub(crate) struct Decoder {
+    layers: Box<dyn Layer>,
+    /// How many of the decoded bytes in the sink have been consumed.
+    position: usize,
+    started: bool,
+    finished: bool,
+}
+
+impl Decoder {
+    /// Returns a decoder for the `Content-Encoding` of a response, and
+    /// removes the `Content-Encoding` and `Content-Length` headers,
+    /// which describe the encoded body. Returns `None`, leaving the
+    /// headers as they are, if the body is not encoded, or if one of
+    /// its codings is not supported.
+    pub(crate) fn from_headers(headers: &mut Headers) -> Option<Decoder> {
+        let mut layers: Box<dyn Layer> = Box::new(Sink::default());
+        let mut encoded = false;
+        // The codings are listed in the order they were applied, so
+        // each one wraps the decoders of the codings applied before
+        // it, and the bytes go through them in reverse.
+        for coding in headers
+            .get_all("content-encoding")
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+        {
+            layers = if coding.eq_ignore_ascii_case("gzip") || coding.eq_ignore_ascii_case("x-gzip")
+            {
+                Box::new(MultiGzDecoder::new(layers))
+            } else if coding.eq_ignore_ascii_case("deflate") {
+                Box::new(Deflate::new(layers))
+            } else if coding.is_empty() || coding.eq_ignore_ascii_case("identity") {
+                continue;
+            } else {
+                return None;
+            };
+            encoded = true;
+        }
+        if !encoded {
+            return None;
+        }
+        headers.remove("content-encoding");
+        headers.remove("content-length");
+        Some(Decoder {
+            layers,
+            position: 0,
+            started: false,
+            finished: false,
+        })
+    }
+
+    /// Decodes `input`, the next bytes of the encoded body. `wire_len`
+    /// is the amount of encoded bytes received so far, including
+    /// `input`, which limits the size of the decoded body to
+    /// `max_ratio` times it.
+    pub(crate) fn decode(
+        &mut self,
+        mut input: &[u8],
+        wire_len: usize,
+        max_ratio: Option<u32>,
+    ) -> Result<(), Error> {
+        let sink = self.layers.sink_mut();
+        sink.buffer.drain(..self.position);
+        self.position = 0;
+        sink.max_total = match max_ratio {
+            Some(ratio) => wire_len.saturating_mul(ratio as usize).max(MIN_RATIO_LIMIT),
+            None => usize::MAX,
+        };
+        self.started = true;
+        while !input.is_empty() {
+            match self.layers.write(input).map_err(decode_error)? {
+                0 => {
+                    return Err(Error::Decompression(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "data after the end of the compressed body",
+                    )))
+                }
+                length => input = &input[length..],
+            }
+        }
+        self.layers.flush().map_err(decode_error)
+    }
+
+    /// Checks that the encoded body ended properly, after all of it
+    /// has been decoded.
+    pub(crate) fn finish(&mut self) -> Result<(), Error> {
+        if self.started && !self.finished {
+            self.layers.finish().map_err(decode_error)?;
+        }
+        self.finished = true;
+        Ok(())
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns the decoded bytes that haven't been consumed yet.
+    pub(crate) fn buffer(&self) -> &[u8] {
+        &self.layers.sink().buffer[self.position..]
+    }
+
+    pub(crate) fn consume(&mut self, amt: usize) -> usize {
+        let amt = amt.min(self.buffer().len());
+        self.position += amt;
+        amt
+    }
+}
+
+/// Returns the errors of the sink as they are, and the others as
+/// [`Decompression`](enum.Error.html#variant.Decompression) errors.
+fn decode_error(err: io::Error) -> Error {
+    if err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+        Error::from(err)
+    } else {
+        Error::Decompression(err)
+    }
+}
+
+/// A decoder in the stack, or the sink at the bottom of it.
+trait Layer: Write + Send {
+    /// Checks that the coding's data ended properly, and writes out
+    /// the rest of the decoded bytes.
+    fn finish(&mut self) -> io::Result<()>;
+    fn sink(&self) -> &Sink;
+    fn sink_mut(&mut self) -> &mut Sink;
+}
+
+/// Collects the decoded body.
+#[derive(Default)]
+struct Sink {
+    buffer: Vec<u8>,
+    total: usize,
+    max_total: usize,
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.total.saturating_add(buf.len()) > self.max_total {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                Error::DecompressionRatioTooHigh,
+            ));
+        }
+        self.total += buf.len();
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Layer for Sink {
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn sink(&self) -> &Sink {
+        self
+    }
+
+    fn sink_mut(&mut self) -> &mut Sink {
+        self
+    }
+}
+
+impl Layer for MultiGzDecoder<Box<dyn Layer>> {
+    fn finish(&mut self) -> io::Result<()> {
+        self.try_finish()?;
+        self.get_mut().finish()
+    }
+
+    fn sink(&self) -> &Sink {
+        self.get_ref().sink()
+    }
+
+    fn sink_mut(&mut self) -> &mut Sink {
+        self.get_mut().sink_mut()
+    }
+}
+
+/// Decodes the `deflate` coding, which is zlib data (RFC 1950), but
+/// which some servers send as raw deflate data (RFC 1951) instead.
+/// The two are told apart by the first two bytes, which are a valid
+/// zlib header only in the first case.
+struct Deflate {
+    head: Vec<u8>,
+    state: DeflateState,
+}
+
+enum DeflateState {
+    Sniffing(Box<dyn Layer>),
+    Zlib(ZlibDecoder<Box<dyn Layer>>),
+    Raw(DeflateDecoder<Box<dyn Layer>>),
+}
+
+impl Deflate {
+    fn new(inner: Box<dyn Layer>) -> Deflate {
+        Deflate {
+            head: Vec::with_capacity(2),
+            state: DeflateState::Sniffing(inner),
+        }
+    }
+
+    /// Picks the decoder from the first two bytes, and writes them
+    /// into it.
+    fn start(&mut self) -> io::Result<()> {
+        let placeholder = DeflateState::Sniffing(Box::new(Sink::default()));
+        if let DeflateState::Sniffing(inner) = mem::replace(&mut self.state, placeholder) {
+            let header = u16::from_be_bytes([self.head[0], self.head[1]]);
+            self.state = if self.head[0] & 0x0f == 8 && header.is_multiple_of(31) {
+                DeflateState::Zlib(ZlibDecoder::new(inner))
+            } else {
+                DeflateState::Raw(DeflateDecoder::new(inner))
+            };
+        }
+        let head = mem::take(&mut self.head);
+        self.write_all(&head)
+    }
+}
+
+impl Write for Deflate {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.state {
+            DeflateState::Zlib(decoder) => decoder.write(buf),
+            DeflateState::Raw(decoder) => decoder.write(buf),
+            DeflateState::Sniffing(_) => {
+                let length = buf.len().min(2 - self.head.len());
+                self.head.extend_from_slice(&buf[..length]);
+                if self.head.len() == 2 {
+                    self.start()?;
+                }
+                Ok(length)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.state {
+            DeflateState::Zlib(decoder) => decoder.flush(),
+            DeflateState::Raw(decoder) => decoder.flush(),
+            DeflateState::Sniffing(inner) => inner.flush(),
+        }
+    }
+}
+
+impl Layer for Deflate {
+    fn finish(&mut self) -> io::Result<()> {
+        match &mut self.state {
+            DeflateState::Zlib(decoder) => {
+                decoder.try_finish()?;
+                decoder.get_mut().finish()
+            }
+            DeflateState::Raw(decoder) => {
+                decoder.try_finish()?;
+                decoder.get_mut().finish()
+            }
+            DeflateState::Sniffing(_) if !self.head.is_empty() => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the deflate data ended early",
+            )),
+            DeflateState::Sniffing(inner) => inner.finish(),
+        }
+    }
+
+    fn sink(&self) -> &Sink {
+        match &self.state {
+            DeflateState::Zlib(decoder) => decoder.get_ref().sink(),
+            DeflateState::Raw(decoder) => decoder.get_ref().sink(),
+            DeflateState::Sniffing(inner) => inner.sink(),
+        }
+    }
+
+    fn sink_mut(&mut self) -> &mut Sink {
+        match &mut self.state {
+            DeflateState::Zlib(decoder) => decoder.get_mut().sink_mut(),
+            DeflateState::Raw(decoder) => decoder.get_mut().sink_mut(),
+            DeflateState::Sniffing(inner) => inner.sink_mut(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decoder;
+    use crate::{Error, Headers};
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decoder(content_encoding: &str) -> Decoder {
+        let mut headers = Headers::new();
+        headers.insert("Content-Encoding", content_encoding);
+        headers.insert("Content-Length", "1");
+        let decoder = Decoder::from_headers(&mut headers).unwrap();
+        assert!(headers.is_empty());
+        decoder
+    }
+
+    /// Decodes `input` a few bytes at a time.
+    fn decode(decoder: &mut Decoder, input: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut decoded = Vec::new();
+        for (i, chunk) in input.chunks(3).enumerate() {
+            decoder.decode(chunk, (i + 1) * 3, Some(100))?;
+            decoded.extend_from_slice(decoder.buffer());
+            let length = decoder.buffer().len();
+            decoder.consume(length);
+        }
+        decoder.finish()?;
+        decoded.extend_from_slice(decoder.buffer());
+        Ok(decoded)
+    }
+
+    #[test]
+    fn decodes_gzip() {
+        let body = gzip(b"Hello, World!");
+        assert_eq!(
+            decode(&mut decoder("gzip"), &body).unwrap(),
+            b"Hello, World!"
+        );
+    }
+
+    #[test]
+    fn decodes_zlib_and_raw_deflate() {
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(b"zlib").unwrap();
+        let zlib = zlib.finish().unwrap();
+        assert_eq!(decode(&mut decoder("deflate"), &zlib).unwrap(), b"zlib");
+
+        let mut raw = DeflateEncoder::new(Vec::new(), Compression::default());
+        raw.write_all(b"raw").unwrap();
+        let raw = raw.finish().unwrap();
+        assert_eq!(decode(&mut decoder("Deflate"), &raw).unwrap(), b"raw");
+    }
+
+    #[test]
+    fn decodes_stacked_codings() {
+        let mut deflate = ZlibEncoder::new(Vec::new(), Compression::default());
+        deflate.write_all(&gzip(b"twice")).unwrap();
+        let body = deflate.finish().unwrap();
+        let mut decoder = decoder("gzip, identity, deflate");
+        assert_eq!(decode(&mut decoder, &body).unwrap(), b"twice");
+    }
+
+    #[test]
+    fn leaves_unsupported_codings() {
+        let mut headers = Headers::new();
+        headers.insert("Content-Encoding", "gzip, br");
+        assert!(Decoder::from_headers(&mut headers).is_none());
+        assert_eq!(headers.get("content-encoding"), Some("gzip, br"));
+        headers.insert("Content-Encoding", "identity");
+        assert!(Decoder::from_headers(&mut headers).is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_and_corrupt_bodies() {
+        let body = gzip(b"Hello, World!");
+        let result = decode(&mut decoder("gzip"), &body[..body.len() - 4]);
+        assert!(matches!(result, Err(Error::Decompression(_))));
+        let result = decode(&mut decoder("gzip"), b"not gzip at all");
+        assert!(matches!(result, Err(Error::Decompression(_))));
+    }
+
+    #[test]
+    fn limits_the_ratio() {
+        let body = gzip(&vec![0; 4 * 1024 * 1024]);
+        let result = decode(&mut decoder("gzip"), &body);
+        assert!(matches!(result, Err(Error::DecompressionRatioTooHigh)));
+
+        let mut decoder = decoder("gzip");
+        decoder.decode(&body, body.len(), None).unwrap();
+        decoder.finish().unwrap();
+        assert_eq!(decoder.buffer().len(), 4 * 1024 * 1024);
+    }
+}
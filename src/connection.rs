@@ -1,33 +1,59 @@
-use crate::{Error, Request, ResponseLazy};
+use crate::body::BodyWriter;
+use crate::response::{buffer_stream, HttpStreamReader};
+use crate::{Error, Request, ResponseLazy, StatusCode, Timings};
 #[cfg(feature = "https")]
 use native_tls::{TlsConnector, TlsStream};
-use std::io::{self, BufReader, BufWriter, Read, Write};
-use std::net::TcpStream;
-#[cfg(feature = "timeout")]
-use std::net::ToSocketAddrs;
-#[cfg(feature = "timeout")]
-use std::time::Duration;
-use std::time::Instant;
-
-type UnsecuredStream = BufReader<TcpStream>;
+use std::io::{self, BufRead, BufWriter, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
 #[cfg(feature = "https")]
 type SecuredStream = TlsStream<TcpStream>;
 
 pub(crate) enum HttpStream {
-    Unsecured(UnsecuredStream, Option<Instant>),
+    Unsecured(TcpStream, Option<Instant>),
     #[cfg(feature = "https")]
     Secured(Box<SecuredStream>, Option<Instant>),
 }
 
 impl HttpStream {
-    fn create_unsecured(reader: UnsecuredStream, timeout_at: Option<Instant>) -> HttpStream {
-        HttpStream::Unsecured(reader, timeout_at)
+    fn create_unsecured(tcp: TcpStream, timeout_at: Option<Instant>) -> HttpStream {
+        HttpStream::Unsecured(tcp, timeout_at)
+    }
+
+    /// Returns the underlying TCP connection.
+    pub(crate) fn tcp(&self) -> &TcpStream {
+        match self {
+            HttpStream::Unsecured(inner, _) => inner,
+            #[cfg(feature = "https")]
+            HttpStream::Secured(inner, _) => inner.get_ref(),
+        }
     }
 
     #[cfg(feature = "https")]
     fn create_secured(reader: SecuredStream, timeout_at: Option<Instant>) -> HttpStream {
         HttpStream::Secured(Box::new(reader), timeout_at)
     }
+
+    fn timeout_at(&self) -> Option<Instant> {
+        match self {
+            HttpStream::Unsecured(_, timeout_at) => *timeout_at,
+            #[cfg(feature = "https")]
+            HttpStream::Secured(_, timeout_at) => *timeout_at,
+        }
+    }
+
+    /// Sets the instant reads time out at, or removes the timeout.
+    fn set_timeout_at(&mut self, new_timeout_at: Option<Instant>) {
+        match self {
+            HttpStream::Unsecured(_, timeout_at) => *timeout_at = new_timeout_at,
+            #[cfg(feature = "https")]
+            HttpStream::Secured(_, timeout_at) => *timeout_at = new_timeout_at,
+        }
+        if new_timeout_at.is_none() {
+            self.tcp().set_read_timeout(None).ok();
+        }
+    }
 }
 
 impl Read for HttpStream {
@@ -49,7 +75,7 @@ impl Read for HttpStream {
 
         match self {
             HttpStream::Unsecured(inner, timeout_at) => {
-                timeout(inner.get_ref(), *timeout_at)?;
+                timeout(inner, *timeout_at)?;
                 inner.read(buf)
             }
             #[cfg(feature = "https")]
@@ -61,6 +87,35 @@ impl Read for HttpStream {
     }
 }
 
+impl Write for HttpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            HttpStream::Unsecured(inner, _) => inner.write(buf),
+            #[cfg(feature = "https")]
+            HttpStream::Secured(inner, _) => inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            HttpStream::Unsecured(inner, _) => inner.flush(),
+            #[cfg(feature = "https")]
+            HttpStream::Secured(inner, _) => inner.flush(),
+        }
+    }
+}
+
+impl BodyWriter for BufWriter<&mut HttpStream> {
+    #[cfg(target_os = "linux")]
+    fn plain_socket(&self) -> Option<&TcpStream> {
+        match self.get_ref() {
+            HttpStream::Unsecured(inner, _) => Some(inner),
+            #[cfg(feature = "https")]
+            HttpStream::Secured(_, _) => None,
+        }
+    }
+}
+
 /// A connection to the server for sending
 /// [`Request`](struct.Request.html)s.
 pub struct Connection {
@@ -94,43 +149,35 @@ impl Connection {
     /// connection, and returns a [`Response`](struct.Response.html).
     #[cfg(feature = "https")]
     pub(crate) fn send_https(self) -> Result<ResponseLazy, Error> {
-        let bytes = self.request.as_bytes();
-
-        let dns_name = &self.request.host;
-        // parse_url in response.rs ensures that there is always a
-        // ":port" in the host, which is why this unwrap is safe.
-        let dns_name = dns_name.split(':').next().unwrap();
-        let mut builder = TlsConnector::builder();
-        builder.danger_accept_invalid_certs(self.request.danger_accept_invalid_certs);
-        builder.danger_accept_invalid_hostnames(self.request.danger_accept_invalid_hostnames);
-        let sess = match builder.build() {
-            Ok(sess) => sess,
-            Err(err) => return Err(Error::IoError(io::Error::new(io::ErrorKind::Other, err))),
-        };
+        let bytes = self.request.as_bytes()?;
+        let mut timings = Timings::default();
 
-        let tcp = self.connect()?;
+        let tcp = self.connect(&mut timings)?;
+        let tls = self.handshake(tcp, &mut timings)?;
 
-        // Send request
-        let mut tls = match sess.connect(dns_name, tcp) {
-            Ok(tls) => tls,
-            Err(err) => return Err(Error::IoError(io::Error::new(io::ErrorKind::Other, err))),
-        };
-        // The connection could drop mid-write, so set a timeout
-        tls.write(&bytes)?;
-
-        // Receive request
-        let response = ResponseLazy::from_stream(HttpStream::create_secured(tls, None))?;
-        handle_redirects(self, response)
+        let stream = HttpStream::create_secured(tls, None);
+        self.send_request(stream, &bytes, timings)
     }
 
     /// Sends the [`Request`](struct.Request.html), consumes this
     /// connection, and returns a [`Response`](struct.Response.html).
     #[cfg(all(feature = "https", feature = "timeout"))]
     pub(crate) fn send_https_timeout(self, timeout: Duration) -> Result<ResponseLazy, Error> {
-        let bytes = self.request.as_bytes();
+        let bytes = self.request.as_bytes()?;
         let timeout_duration = self.timeout.map(Duration::from_secs);
         let timeout_at = timeout_duration.map(|d| Instant::now() + d);
+        let mut timings = Timings::default();
+
+        let tcp = self.connect_timeout(timeout, &mut timings)?;
+        let tls = self.handshake(tcp, &mut timings)?;
+
+        let stream = HttpStream::create_secured(tls, timeout_at);
+        self.send_request(stream, &bytes, timings)
+    }
 
+    /// Performs the TLS handshake with the host over `tcp`.
+    #[cfg(feature = "https")]
+    fn handshake(&self, tcp: TcpStream, timings: &mut Timings) -> Result<SecuredStream, Error> {
         let dns_name = &self.request.host;
         // parse_url in response.rs ensures that there is always a
         // ":port" in the host, which is why this unwrap is safe.
@@ -140,91 +187,191 @@ impl Connection {
         builder.danger_accept_invalid_hostnames(self.request.danger_accept_invalid_hostnames);
         let sess = match builder.build() {
             Ok(sess) => sess,
-            Err(err) => return Err(Error::IoError(io::Error::new(io::ErrorKind::Other, err))),
+            Err(err) => return Err(Error::IoError(io::Error::other(err))),
         };
 
-        let tcp = self.connect_timeout(timeout)?;
-
-        // Send request
-        let mut tls = match sess.connect(dns_name, tcp) {
+        let handshake_start = Instant::now();
+        let tls = match sess.connect(dns_name, tcp) {
             Ok(tls) => tls,
-            Err(err) => return Err(Error::IoError(io::Error::new(io::ErrorKind::Other, err))),
+            Err(err) => return Err(Error::IoError(io::Error::other(err))),
         };
-        // The connection could drop mid-write, so set a timeout
-        tls.write(&bytes)?;
-
-        // Receive request
-        let response = ResponseLazy::from_stream(HttpStream::create_secured(tls, timeout_at))?;
-        handle_redirects(self, response)
+        timings.tls_handshake = Some(handshake_start.elapsed());
+        Ok(tls)
     }
 
     /// Sends the [`Request`](struct.Request.html), consumes this
     /// connection, and returns a [`Response`](struct.Response.html).
     pub(crate) fn send(self) -> Result<ResponseLazy, Error> {
-        let bytes = self.request.as_bytes();
-        let tcp = self.connect()?;
+        let bytes = self.request.as_bytes()?;
+        let mut timings = Timings::default();
 
-        // Send request
-        let mut stream = BufWriter::new(tcp);
-        stream.write_all(&bytes)?;
-
-        // Receive response
-        let tcp = match stream.into_inner() {
-            Ok(tcp) => tcp,
-            Err(_) => {
-                return Err(Error::Other(
-                    "IntoInnerError after writing the request into the TcpStream.",
-                ));
-            }
-        };
-        let stream = HttpStream::create_unsecured(BufReader::new(tcp), None);
-        let response = ResponseLazy::from_stream(stream)?;
-        handle_redirects(self, response)
-    }
+        let tcp = self.connect(&mut timings)?;
 
-    fn connect(&self) -> Result<TcpStream, Error> {
-        TcpStream::connect(&self.request.host).map_err(Error::from)
+        let stream = HttpStream::create_unsecured(tcp, None);
+        self.send_request(stream, &bytes, timings)
     }
 
     /// Sends the [`Request`](struct.Request.html), consumes this
     /// connection, and returns a [`Response`](struct.Response.html).
     #[cfg(feature = "timeout")]
-    #[allow(dead_code)]
     pub(crate) fn send_timeout(self, timeout: Duration) -> Result<ResponseLazy, Error> {
-        let bytes = self.request.as_bytes();
+        let bytes = self.request.as_bytes()?;
         let timeout_duration = self.timeout.map(Duration::from_secs);
         let timeout_at = timeout_duration.map(|d| Instant::now() + d);
+        let mut timings = Timings::default();
+
+        let tcp = self.connect_timeout(timeout, &mut timings)?;
+        // The connection could drop mid-write, so set a timeout
+        tcp.set_write_timeout(timeout_duration).ok();
 
-        let tcp = self.connect_timeout(timeout)?;
+        let stream = HttpStream::create_unsecured(tcp, timeout_at);
+        self.send_request(stream, &bytes, timings)
+    }
+
+    /// Writes the request into the connected `stream`, and reads the
+    /// response, following redirections.
+    fn send_request(
+        self,
+        stream: HttpStream,
+        bytes: &[u8],
+        mut timings: Timings,
+    ) -> Result<ResponseLazy, Error> {
+        let mut stream = buffer_stream(stream);
+        let parse_options = self.request.parse_options();
+        let expect_continue = self.request.expect_continue_timeout();
 
         // Send request
-        let mut stream = BufWriter::new(tcp);
-        stream.get_ref().set_write_timeout(timeout_duration).ok();
-        stream.write_all(&bytes)?;
+        let write_start = Instant::now();
+        write_request(
+            &self.request,
+            stream.get_mut(),
+            bytes,
+            expect_continue.is_none(),
+        )?;
+        let mut request_sent = Instant::now();
+        timings.request_write = request_sent - write_start;
 
-        // Receive response
-        let tcp = match stream.into_inner() {
-            Ok(tcp) => tcp,
-            Err(_) => {
-                return Err(Error::Other(
-                    "IntoInnerError after writing the request into the TcpStream.",
-                ));
+        if let Some(wait) = expect_continue {
+            // Only send the body after the server asks for it, or
+            // doesn't answer in time.
+            if wait_for_response(&mut stream, wait)? {
+                let response =
+                    ResponseLazy::from_stream(stream, parse_options, timings, request_sent, true)?;
+                if response.status_code == StatusCode::EXPECTATION_FAILED {
+                    // The server doesn't support the expectation, so
+                    // try again without it.
+                    let mut request = self.request.remove_header("expect");
+                    request.expect_continue = None;
+                    let mut retried = request.send_lazy()?;
+                    retried.timings.expectation_failed = Some(Box::new(response.timings));
+                    return Ok(retried);
+                } else if response.status_code != StatusCode::CONTINUE {
+                    return handle_redirects(self, response);
+                }
+                (stream, timings) = response.into_stream();
             }
-        };
-        let stream = HttpStream::create_unsecured(BufReader::new(tcp), timeout_at);
-        let response = ResponseLazy::from_stream(stream)?;
+            let body_start = Instant::now();
+            write_request(&self.request, stream.get_mut(), &[], true)?;
+            request_sent = Instant::now();
+            timings.request_write += request_sent - body_start;
+        }
+
+        // Receive response
+        let response =
+            ResponseLazy::from_stream(stream, parse_options, timings, request_sent, false)?;
         handle_redirects(self, response)
     }
 
+    fn connect(&self, timings: &mut Timings) -> Result<TcpStream, Error> {
+        let dns_start = Instant::now();
+        let addrs: Vec<SocketAddr> = self.request.host.to_socket_addrs()?.collect();
+        let connect_start = Instant::now();
+        timings.dns = connect_start - dns_start;
+
+        let tcp = TcpStream::connect(&addrs[..])?;
+        timings.connect = connect_start.elapsed();
+        Ok(tcp)
+    }
+
+    /// Connects to the first resolved address that accepts the
+    /// connection. `timeout` is shared by all the attempts, so each
+    /// address only gets the time that is left.
     #[cfg(feature = "timeout")]
-    fn connect_timeout(&self, timeout: Duration) -> Result<TcpStream, Error> {
-        let addr = self
-            .request
-            .host
-            .to_socket_addrs()?
-            .next()
-            .ok_or(Error::Other("Failed to resolve host to SocketAddr"))?;
-        TcpStream::connect_timeout(&addr, timeout).map_err(Error::from)
+    fn connect_timeout(
+        &self,
+        timeout: Duration,
+        timings: &mut Timings,
+    ) -> Result<TcpStream, Error> {
+        let dns_start = Instant::now();
+        let timeout_at = dns_start + timeout;
+        let addrs = self.request.host.to_socket_addrs()?;
+        let connect_start = Instant::now();
+        timings.dns = connect_start - dns_start;
+
+        let mut last_error = None;
+        for addr in addrs {
+            let remaining = timeout_at.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::IoError(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "The request's timeout was reached.",
+                )));
+            }
+            match TcpStream::connect_timeout(&addr, remaining) {
+                Ok(tcp) => {
+                    timings.connect = connect_start.elapsed();
+                    return Ok(tcp);
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+        match last_error {
+            Some(err) => Err(Error::IoError(err)),
+            None => Err(Error::Other("Failed to resolve host to SocketAddr")),
+        }
+    }
+}
+
+/// Writes `head` into `stream`, followed by the body of the request if
+/// `with_body` is set.
+fn write_request(
+    request: &Request,
+    stream: &mut HttpStream,
+    head: &[u8],
+    with_body: bool,
+) -> Result<(), Error> {
+    // Buffer the streamed body, so that small chunks don't go out as
+    // packets of their own.
+    let mut writer = BufWriter::with_capacity(16 * 1024, stream);
+    writer.write_all(head)?;
+    if with_body {
+        request.write_streamed_body(&mut writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Waits at most `wait` for the server to start responding. Returns
+/// `false` if it didn't.
+fn wait_for_response(stream: &mut HttpStreamReader, wait: Duration) -> Result<bool, Error> {
+    let timeout_at = stream.get_ref().timeout_at();
+    let wait_until = Instant::now() + wait;
+    let wait_until = timeout_at.map_or(wait_until, |timeout_at| timeout_at.min(wait_until));
+    stream.get_mut().set_timeout_at(Some(wait_until));
+    let result = stream.fill_buf().map(|_| ());
+    stream.get_mut().set_timeout_at(timeout_at);
+
+    match result {
+        Ok(()) => Ok(true),
+        Err(err)
+            if matches!(
+                err.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) && timeout_at.is_none_or(|timeout_at| Instant::now() < timeout_at) =>
+        {
+            Ok(false)
+        }
+        Err(err) => Err(Error::IoError(err)),
     }
 }
 
@@ -232,7 +379,9 @@ fn handle_redirects(connection: Connection, response: ResponseLazy) -> Result<Re
     let status_code = response.status_code;
     let url = response.headers.get("location");
     if let Some(request) = get_redirect(connection, status_code, url) {
-        request?.send_lazy()
+        let mut redirected = request?.send_lazy()?;
+        redirected.timings.redirects.insert(0, response.timings);
+        Ok(redirected)
     } else {
         Ok(response)
     }
@@ -240,12 +389,12 @@ fn handle_redirects(connection: Connection, response: ResponseLazy) -> Result<Re
 
 fn get_redirect(
     connection: Connection,
-    status_code: i32,
-    url: Option<&String>,
+    status_code: StatusCode,
+    url: Option<&str>,
 ) -> Option<Result<Request, Error>> {
-    match status_code {
+    match status_code.as_u16() {
         301 | 302 | 303 | 307 => match url {
-            Some(url) => Some(connection.request.redirect_to(url.clone())),
+            Some(url) => Some(connection.request.redirect_to(url.to_string())),
             None => Some(Err(Error::RedirectLocationMissing)),
         },
 
@@ -0,0 +1,207 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Formats `time` as an HTTP-date (RFC 9110, section 5.6.7) in the
+/// preferred IMF-fixdate format, eg.
+/// `Sun, 06 Nov 1994 08:49:37 GMT`. Times before 1970 are formatted
+/// as the start of 1970.
+pub(crate) fn format(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) as i64;
+    let days = seconds.div_euclid(SECONDS_PER_DAY);
+    let time_of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[days.rem_euclid(7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60
+    )
+}
+
+/// Parses an HTTP-date in any of the three formats that recipients
+/// must accept: IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`), and
+/// the obsolete RFC 850 (`Sunday, 06-Nov-94 08:49:37 GMT`) and
+/// asctime (`Sun Nov  6 08:49:37 1994`) formats. The weekday is not
+/// checked against the date.
+pub(crate) fn parse(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    let (year, month, day, time) = if let Some((_, rest)) = value.split_once(", ") {
+        let parts: Vec<&str> = rest.split(' ').collect();
+        match parts[..] {
+            // IMF-fixdate
+            [day, month, year, time, "GMT"] if day.len() == 2 && year.len() == 4 => {
+                (parse_digits(year)?, month, day, time)
+            }
+            // RFC 850
+            [date, time, "GMT"] => {
+                let mut date = date.split('-');
+                let (day, month, year) = (date.next()?, date.next()?, date.next()?);
+                if day.len() != 2 || year.len() != 2 || date.next().is_some() {
+                    return None;
+                }
+                (expand_two_digit_year(parse_digits(year)?), month, day, time)
+            }
+            _ => return None,
+        }
+    } else {
+        // asctime, with the day padded to two characters with a space
+        let rest = value.get(4..)?;
+        let (month, rest) = rest.split_once(' ')?;
+        let rest = rest.strip_prefix(' ').unwrap_or(rest);
+        let parts: Vec<&str> = rest.split(' ').collect();
+        match parts[..] {
+            [day, time, year] if year.len() == 4 => (parse_digits(year)?, month, day, time),
+            _ => return None,
+        }
+    };
+
+    let month = MONTHS.iter().position(|&name| name == month)? as u32 + 1;
+    let day: u32 = parse_digits(day)?;
+    if day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    let mut time = time.split(':');
+    let (hour, minute, second) = (time.next()?, time.next()?, time.next()?);
+    if time.next().is_some() || hour.len() != 2 || minute.len() != 2 || second.len() != 2 {
+        return None;
+    }
+    let (hour, minute, second): (i64, i64, i64) = (
+        parse_digits(hour)?,
+        parse_digits(minute)?,
+        parse_digits(second)?,
+    );
+    // A leap second is taken as the last second of the minute.
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY
+        + hour * 3600
+        + minute * 60
+        + second.min(59);
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+    }
+}
+
+fn parse_digits<T: std::str::FromStr>(digits: &str) -> Option<T> {
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Expands the two-digit year of an RFC 850 date into the most recent
+/// year with those digits that is not over 50 years in the future.
+fn expand_two_digit_year(year: i64) -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs()) as i64;
+    let (current_year, _, _) = civil_from_days(now / SECONDS_PER_DAY);
+    let year = current_year - current_year.rem_euclid(100) + year;
+    if year > current_year + 50 {
+        year - 100
+    } else {
+        year
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Returns the number of days since 1970-01-01 of a date in the
+/// proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format, parse};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn parses_all_three_formats() {
+        let expected = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        assert_eq!(parse("Sun, 06 Nov 1994 08:49:37 GMT"), Some(expected));
+        assert_eq!(parse("Sunday, 06-Nov-94 08:49:37 GMT"), Some(expected));
+        assert_eq!(parse("Sun Nov  6 08:49:37 1994"), Some(expected));
+        assert_eq!(
+            parse("Sun Nov 16 08:49:37 1994"),
+            Some(expected + Duration::from_secs(864_000))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        for value in [
+            "0",
+            "",
+            "Sun, 06 Nov 1994 08:49:37 PST",
+            "Sun, 6 Nov 1994 08:49:37 GMT",
+            "Sun, 31 Nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 24:00:00 GMT",
+            "Sun, 06 Nov 1994 08:49 GMT",
+            "Sun, 06 Foo 1994 08:49:37 GMT",
+            "Sun, 06 Nov +994 08:49:37 GMT",
+            "Sunday, 06-Nov-1994 08:49:37 GMT",
+        ] {
+            assert_eq!(parse(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn formats_imf_fixdates() {
+        let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        assert_eq!(format(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(format(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+        let leap_day = parse("Thu, 29 Feb 2024 23:59:59 GMT").unwrap();
+        assert_eq!(format(leap_day), "Thu, 29 Feb 2024 23:59:59 GMT");
+        assert_eq!(parse(&format(leap_day)), Some(leap_day));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::{Headers, StatusCode};
 use std::{error, fmt, io, str};
 
 /// Represents an error while sending, receiving, or parsing an HTTP response.
@@ -6,6 +7,13 @@ pub enum Error {
     /// The response body contains invalid UTF-8, so the `as_str()`
     /// conversion failed.
     InvalidUtf8InBody(str::Utf8Error),
+    /// The response body is not valid in the charset it was decoded
+    /// from with [`Response::text`](struct.Response.html#method.text).
+    /// Contains the name of the charset.
+    InvalidTextInBody(&'static str),
+    /// The charset of the response body is unknown, or needs the
+    /// `charset` feature to be decoded. Contains the charset's label.
+    UnsupportedCharset(String),
 
     /// Ran into an IO problem while loading the response.
     IoError(io::Error),
@@ -13,10 +21,41 @@ pub enum Error {
     /// response with the header `Transfer-Encoding: chunked`.
     MalformedChunkLength,
     /// Couldn't parse the `Content-Length` header's value as an
-    /// `usize`.
+    /// `usize`, or the response had multiple `Content-Length` values
+    /// that disagree.
     MalformedContentLength,
     /// Couldn't parse the response status line.
     MalformedStatusLine,
+    /// The response's status line did not start with `HTTP/1.x`.
+    UnsupportedHttpVersion,
+    /// A header or trailer field of the response did not have a name
+    /// followed directly by a `:`. Only returned in
+    /// [`ParseMode::Strict`](enum.ParseMode.html#variant.Strict).
+    MalformedHeader,
+    /// A line of the response's head ended in a bare `\n` instead of
+    /// `\r\n`. Only returned in
+    /// [`ParseMode::Strict`](enum.ParseMode.html#variant.Strict).
+    BareLineFeed,
+    /// A header or trailer field value of the response was folded
+    /// onto multiple lines (obs-fold), which RFC 9112 deprecates.
+    /// Only returned in
+    /// [`ParseMode::Strict`](enum.ParseMode.html#variant.Strict).
+    ObsoleteLineFolding,
+    /// The URI template passed to
+    /// [`Request::from_template`](struct.Request.html#method.from_template)
+    /// has unbalanced braces, an empty expression, or an expression
+    /// that is not supported.
+    MalformedTemplate,
+    /// Couldn't decode the response body's `Content-Encoding`, eg.
+    /// because the compressed data is corrupt or was cut short.
+    #[cfg(feature = "compression")]
+    Decompression(io::Error),
+    /// The response body decompressed to more than
+    /// [`max_decompression_ratio`](struct.Request.html#method.with_max_decompression_ratio)
+    /// times its compressed size, which is a sign of a decompression
+    /// bomb.
+    #[cfg(feature = "compression")]
+    DecompressionRatioTooHigh,
     /// The response was a redirection, but the `Location` header is
     /// missing.
     RedirectLocationMissing,
@@ -26,9 +65,92 @@ pub enum Error {
     /// [`max_redirections`](struct.Request.html#method.with_max_redirections)
     /// redirections, won't follow any more.
     TooManyRedirections,
+    /// A line of the response's head (the status line, a header or
+    /// a chunk size line) was longer than
+    /// [`max_line_length`](struct.Request.html#method.with_max_line_length).
+    LineTooLong,
+    /// The response had more header fields than
+    /// [`max_header_count`](struct.Request.html#method.with_max_header_count).
+    TooManyHeaders,
+    /// The response's header fields were larger in total than
+    /// [`max_headers_size`](struct.Request.html#method.with_max_headers_size).
+    HeadersTooLarge,
+    /// The response body was larger than
+    /// [`max_body_size`](struct.Request.html#method.with_max_body_size).
+    BodyTooLarge,
     /// The response contained invalid UTF-8 where it should be valid
     /// (eg. headers), so the response cannot interpreted correctly.
     InvalidUtf8InResponse,
+    /// The request's method is not a valid token, eg. it contains
+    /// whitespace or control characters. Contains the method.
+    InvalidMethod(String),
+    /// A header of the request has a name that is not a valid token,
+    /// or a value that contains `\r`, `\n` or NUL, which could inject
+    /// extra headers into the request. Contains the header's name.
+    InvalidHeader(String),
+    /// The request's url contains control characters, which could
+    /// inject extra lines into the request.
+    InvalidRequestTarget,
+    /// The request's body is streamed from a reader, which was already
+    /// read when the request was sent before, eg. before following a
+    /// redirection.
+    BodyAlreadySent,
+    /// The response's status code was a client error (4xx) or a
+    /// server error (5xx). Returned by
+    /// [`Response::error_for_status`](struct.Response.html#method.error_for_status).
+    HttpStatus {
+        /// The status code of the response.
+        status_code: StatusCode,
+        /// The reason phrase of the response.
+        reason_phrase: String,
+        /// The headers of the response.
+        headers: Headers,
+        /// The start of the response's body, at most a kilobyte.
+        body: Vec<u8>,
+    },
+    /// Couldn't turn a value into query parameters or form fields,
+    /// eg. because it is not a struct or a map, or has nested structs.
+    /// Contains a description of the problem.
+    #[cfg(feature = "serde")]
+    SerializePairs(String),
+    /// Couldn't serialize a request body into JSON, or deserialize a
+    /// response body from it.
+    #[cfg(feature = "json")]
+    Json {
+        /// The error from `serde_json`.
+        error: serde_json::Error,
+        /// How many bytes of the body had been read when the error
+        /// was detected, or `None` for errors that are not tied to a
+        /// position, like serialization errors.
+        offset: Option<usize>,
+    },
+    /// Couldn't serialize a request body into CBOR, or deserialize a
+    /// response body from it.
+    #[cfg(feature = "cbor")]
+    Cbor {
+        /// A description of the problem.
+        message: String,
+        /// The byte offset in the body the error was detected at, if
+        /// it is known.
+        offset: Option<usize>,
+    },
+    /// Couldn't serialize a request body into MessagePack, or
+    /// deserialize a response body from it. Contains a description of
+    /// the problem.
+    #[cfg(feature = "msgpack")]
+    MsgPack(String),
+    /// The response's `Content-Type` did not match the format it was
+    /// decoded as, eg. when calling
+    /// [`Response::cbor`](struct.Response.html#method.cbor) on an
+    /// HTML error page.
+    #[cfg(any(feature = "cbor", feature = "msgpack"))]
+    UnexpectedContentType {
+        /// The content type the body was expected to have.
+        expected: &'static str,
+        /// The `Content-Type` of the response, or `None` if it had
+        /// none.
+        content_type: Option<String>,
+    },
     /// Tried to send a secure request (ie. the url started with
     /// `https://`), but the crate's `https` feature was not enabled,
     /// and as such, a connection cannot be made.
@@ -47,14 +169,50 @@ impl fmt::Display for Error {
         match self {
             IoError(err) => write!(f, "{}", err),
             InvalidUtf8InBody(err) => write!(f, "{}", err),
+            InvalidTextInBody(charset) => write!(f, "response body is not valid {}", charset),
+            UnsupportedCharset(label) => write!(f, "unsupported charset {:?}", label),
 
             MalformedChunkLength => write!(f, "non-usize chunk length with transfer-encoding: chunked"),
             MalformedContentLength => write!(f, "non-usize content length"),
             MalformedStatusLine => write!(f, "malformed status line"),
+            UnsupportedHttpVersion => write!(f, "response status line does not start with HTTP/1.x"),
+            MalformedHeader => write!(f, "malformed header field (expected a name followed by ':')"),
+            BareLineFeed => write!(f, "line in the response head ended in a bare \\n instead of \\r\\n"),
+            ObsoleteLineFolding => write!(f, "header field value folded onto multiple lines (obs-fold)"),
+            MalformedTemplate => write!(f, "malformed uri template"),
+            #[cfg(feature = "compression")]
+            Decompression(err) => write!(f, "couldn't decompress the response body: {}", err),
+            #[cfg(feature = "compression")]
+            DecompressionRatioTooHigh => write!(f, "the response body decompressed to over the max ratio of its compressed size"),
             RedirectLocationMissing => write!(f, "redirection location header missing"),
             InfiniteRedirectionLoop => write!(f, "infinite redirection loop detected"),
             TooManyRedirections => write!(f, "too many redirections (over the max)"),
+            LineTooLong => write!(f, "a line in the response head is over the max length"),
+            TooManyHeaders => write!(f, "the response has too many header fields (over the max)"),
+            HeadersTooLarge => write!(f, "the response header fields are too large (over the max)"),
+            BodyTooLarge => write!(f, "the response body is too large (over the max)"),
             InvalidUtf8InResponse => write!(f, "response contained invalid utf-8 where valid utf-8 was expected"),
+            InvalidMethod(method) => write!(f, "invalid request method {:?}", method),
+            InvalidHeader(name) => write!(f, "invalid request header {:?}", name),
+            InvalidRequestTarget => write!(f, "request url contains control characters"),
+            BodyAlreadySent => write!(f, "the streamed request body was already sent and can't be sent again"),
+            HttpStatus { status_code, reason_phrase, .. } => write!(f, "http status {} {}", status_code, reason_phrase),
+            #[cfg(feature = "serde")]
+            SerializePairs(msg) => write!(f, "couldn't serialize into query parameters or form fields: {}", msg),
+            #[cfg(feature = "json")]
+            Json { error, offset: Some(offset) } => write!(f, "invalid json at byte {}: {}", offset, error),
+            #[cfg(feature = "json")]
+            Json { error, offset: None } => write!(f, "json error: {}", error),
+            #[cfg(feature = "cbor")]
+            Cbor { message, offset: Some(offset) } => write!(f, "invalid cbor at byte {}: {}", offset, message),
+            #[cfg(feature = "cbor")]
+            Cbor { message, offset: None } => write!(f, "cbor error: {}", message),
+            #[cfg(feature = "msgpack")]
+            MsgPack(message) => write!(f, "msgpack error: {}", message),
+            #[cfg(any(feature = "cbor", feature = "msgpack"))]
+            UnexpectedContentType { expected, content_type: Some(content_type) } => write!(f, "expected a {} response body, but the content type is {:?}", expected, content_type),
+            #[cfg(any(feature = "cbor", feature = "msgpack"))]
+            UnexpectedContentType { expected, content_type: None } => write!(f, "expected a {} response body, but the response has no content type", expected),
             HttpsFeatureNotEnabled => write!(f, "request url contains https:// but the https feature is not enabled"),
             Other(msg) => write!(f, "error in tinyget: please open an issue in the tinyget repo, include the following: '{}'", msg),
         }
@@ -67,6 +225,10 @@ impl error::Error for Error {
         match self {
             IoError(err) => Some(err),
             InvalidUtf8InBody(err) => Some(err),
+            #[cfg(feature = "compression")]
+            Decompression(err) => Some(err),
+            #[cfg(feature = "json")]
+            Json { error, .. } => Some(error),
             _ => None,
         }
     }
@@ -74,6 +236,26 @@ impl error::Error for Error {
 
 impl From<io::Error> for Error {
     fn from(other: io::Error) -> Error {
-        Error::IoError(other)
+        // The Read implementation of ResponseLazy wraps its errors in
+        // io::Errors, so unwrap those. Other errors are kept whole, as
+        // into_inner would lose their kind.
+        if !other.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            return Error::IoError(other);
+        }
+        let kind = other.kind();
+        match other.into_inner().map(|inner| inner.downcast::<Error>()) {
+            Some(Ok(err)) => *err,
+            Some(Err(inner)) => Error::IoError(io::Error::new(kind, inner)),
+            None => Error::IoError(io::Error::from(kind)),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(other: Error) -> io::Error {
+        match other {
+            Error::IoError(err) => err,
+            other => io::Error::new(io::ErrorKind::InvalidData, other),
+        }
     }
 }
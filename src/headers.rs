@@ -0,0 +1,330 @@
+use crate::{CacheControl, ContentRange, ContentType, ETag, RetryAfter};
+use std::fmt;
+use std::time::SystemTime;
+
+/// An ordered collection of HTTP header fields.
+///
+/// Unlike a `HashMap`, this keeps every value of fields that appear
+/// multiple times (eg. `Set-Cookie`), in the order they were received
+/// or added. Lookups are case-insensitive, but the original casing of
+/// the names is retained for display and iteration.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), tinyget::Error> {
+/// let response = tinyget::get("http://example.com").send()?;
+/// if let Some(content_type) = response.headers.get("content-type") {
+///     println!("{}", content_type);
+/// }
+/// for cookie in response.headers.get_all("set-cookie") {
+///     println!("{}", cookie);
+/// }
+/// for (name, value) in &response.headers {
+///     println!("{}: {}", name, value);
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Headers {
+    fields: Vec<(String, String)>,
+}
+
+impl Headers {
+    /// Creates an empty collection of header fields.
+    pub fn new() -> Headers {
+        Headers { fields: Vec::new() }
+    }
+
+    /// Returns the first value of the field called `name`, if there
+    /// is one. The name is matched case-insensitively.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns all the values of the field called `name`, in order.
+    /// The name is matched case-insensitively.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.fields
+            .iter()
+            .filter(move |(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns `true` if there is at least one field called `name`.
+    /// The name is matched case-insensitively.
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Returns an iterator over the `(name, value)` pairs, in order,
+    /// with the names in their original casing.
+    pub fn iter(&self) -> HeadersIter<'_> {
+        HeadersIter {
+            inner: self.fields.iter(),
+        }
+    }
+
+    /// Returns the amount of fields, counting each value of a
+    /// repeated field separately.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Returns `true` if there are no fields.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Adds a field to the end, keeping any existing values of the
+    /// same field.
+    pub fn append<T: Into<String>, U: Into<String>>(&mut self, name: T, value: U) {
+        self.fields.push((name.into(), value.into()));
+    }
+
+    /// Sets the value of the field called `name`, replacing all of
+    /// its existing values. The field keeps the position of its first
+    /// occurrence, or is added to the end if it didn't exist.
+    pub fn insert<T: Into<String>, U: Into<String>>(&mut self, name: T, value: U) {
+        let name = name.into();
+        let value = value.into();
+        match self
+            .fields
+            .iter()
+            .position(|(field, _)| field.eq_ignore_ascii_case(&name))
+        {
+            Some(index) => {
+                let rest = self.fields.split_off(index + 1);
+                self.fields.extend(
+                    rest.into_iter()
+                        .filter(|(field, _)| !field.eq_ignore_ascii_case(&name)),
+                );
+                self.fields[index] = (name, value);
+            }
+            None => self.fields.push((name, value)),
+        }
+    }
+
+    /// Removes all the values of the field called `name`, and returns
+    /// the first one, if there were any. The name is matched
+    /// case-insensitively.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let mut removed = None;
+        self.fields.retain(|(field, value)| {
+            if field.eq_ignore_ascii_case(name) {
+                if removed.is_none() {
+                    removed = Some(value.clone());
+                }
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    /// Returns the parsed `Content-Type` field. `None` if it is
+    /// missing or malformed.
+    pub fn content_type(&self) -> Option<ContentType> {
+        self.get("content-type").and_then(ContentType::parse)
+    }
+
+    /// Returns the parsed `Content-Length` field. `None` if it is
+    /// missing or not a number.
+    pub fn content_length(&self) -> Option<u64> {
+        let value = self.get("content-length")?.trim();
+        if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        value.parse().ok()
+    }
+
+    /// Returns the directives of all the `Cache-Control` fields. `None`
+    /// if there are none, or they are malformed.
+    pub fn cache_control(&self) -> Option<CacheControl> {
+        if !self.contains_key("cache-control") {
+            return None;
+        }
+        let values: Vec<&str> = self.get_all("cache-control").collect();
+        CacheControl::parse(&values.join(","))
+    }
+
+    /// Returns the parsed `ETag` field. `None` if it is missing or
+    /// malformed.
+    pub fn etag(&self) -> Option<ETag> {
+        self.get("etag").and_then(ETag::parse)
+    }
+
+    /// Returns the parsed `Date` field, when the message was sent.
+    /// `None` if it is missing or not an HTTP-date.
+    pub fn date(&self) -> Option<SystemTime> {
+        self.get("date").and_then(crate::date::parse)
+    }
+
+    /// Returns the parsed `Last-Modified` field. `None` if it is
+    /// missing or not an HTTP-date.
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        self.get("last-modified").and_then(crate::date::parse)
+    }
+
+    /// Returns the parsed `Expires` field. `None` if it is missing or
+    /// not an HTTP-date, eg. `0`, which caches take to mean that the
+    /// response has already expired.
+    pub fn expires(&self) -> Option<SystemTime> {
+        self.get("expires").and_then(crate::date::parse)
+    }
+
+    /// Returns the parsed `Retry-After` field. `None` if it is missing
+    /// or malformed.
+    pub fn retry_after(&self) -> Option<RetryAfter> {
+        self.get("retry-after").and_then(RetryAfter::parse)
+    }
+
+    /// Returns the parsed `Content-Range` field. `None` if it is
+    /// missing, malformed, or not in bytes.
+    pub fn content_range(&self) -> Option<ContentRange> {
+        self.get("content-range").and_then(ContentRange::parse)
+    }
+
+    /// Returns whether an `Accept-Ranges` field lists `bytes`, ie.
+    /// whether byte ranges can be requested, eg. to resume a download.
+    pub fn accepts_byte_ranges(&self) -> bool {
+        self.get_all("accept-ranges")
+            .flat_map(|value| value.split(','))
+            .any(|unit| unit.trim().eq_ignore_ascii_case("bytes"))
+    }
+
+    /// Returns the value of the last field, for appending the
+    /// continuation lines of folded values.
+    pub(crate) fn last_value_mut(&mut self) -> Option<&mut String> {
+        self.fields.last_mut().map(|(_, value)| value)
+    }
+}
+
+impl fmt::Debug for Headers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> IntoIterator for &'a Headers {
+    type Item = (&'a str, &'a str);
+    type IntoIter = HeadersIter<'a>;
+
+    fn into_iter(self) -> HeadersIter<'a> {
+        self.iter()
+    }
+}
+
+impl<T: Into<String>, U: Into<String>> FromIterator<(T, U)> for Headers {
+    fn from_iter<I: IntoIterator<Item = (T, U)>>(iter: I) -> Headers {
+        let mut headers = Headers::new();
+        headers.extend(iter);
+        headers
+    }
+}
+
+impl<T: Into<String>, U: Into<String>> Extend<(T, U)> for Headers {
+    fn extend<I: IntoIterator<Item = (T, U)>>(&mut self, iter: I) {
+        for (name, value) in iter {
+            self.append(name, value);
+        }
+    }
+}
+
+/// An iterator over the fields of [`Headers`](struct.Headers.html),
+/// created by [`Headers::iter`](struct.Headers.html#method.iter).
+pub struct HeadersIter<'a> {
+    inner: std::slice::Iter<'a, (String, String)>,
+}
+
+impl<'a> Iterator for HeadersIter<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Headers;
+
+    #[test]
+    fn keeps_every_value_in_order() {
+        let mut headers = Headers::new();
+        headers.append("Set-Cookie", "a=1");
+        headers.append("Content-Type", "text/plain");
+        headers.append("set-cookie", "b=2");
+
+        assert_eq!(headers.get("SET-COOKIE"), Some("a=1"));
+        let cookies: Vec<&str> = headers.get_all("set-cookie").collect();
+        assert_eq!(cookies, ["a=1", "b=2"]);
+        let names: Vec<&str> = headers.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["Set-Cookie", "Content-Type", "set-cookie"]);
+    }
+
+    #[test]
+    fn insert_replaces_all_values_in_place() {
+        let mut headers = Headers::new();
+        headers.append("Accept", "text/html");
+        headers.append("Host", "example.com");
+        headers.append("accept", "text/plain");
+        headers.insert("ACCEPT", "*/*");
+
+        let fields: Vec<(&str, &str)> = headers.iter().collect();
+        assert_eq!(fields, [("ACCEPT", "*/*"), ("Host", "example.com")]);
+    }
+
+    #[test]
+    fn parses_typed_fields() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let headers: Headers = vec![
+            ("Content-Type", "application/json; charset=utf-8"),
+            ("Content-Length", "42"),
+            ("Cache-Control", "public"),
+            ("cache-control", "max-age=60"),
+            ("ETag", "W/\"v1\""),
+            ("Last-Modified", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            ("Expires", "0"),
+            ("Accept-Ranges", "none, Bytes"),
+        ]
+        .into_iter()
+        .collect();
+
+        let content_type = headers.content_type().unwrap();
+        assert_eq!(content_type.essence(), "application/json");
+        assert_eq!(headers.content_length(), Some(42));
+        let cache_control = headers.cache_control().unwrap();
+        assert!(cache_control.is_public());
+        assert_eq!(cache_control.max_age(), Some(Duration::from_secs(60)));
+        assert_eq!(headers.etag().unwrap().tag(), "v1");
+        assert_eq!(
+            headers.last_modified(),
+            Some(UNIX_EPOCH + Duration::from_secs(784_111_777))
+        );
+        assert_eq!(headers.expires(), None);
+        assert_eq!(headers.date(), None);
+        assert!(headers.accepts_byte_ranges());
+        assert_eq!(Headers::new().cache_control(), None);
+    }
+
+    #[test]
+    fn remove_returns_first_value() {
+        let mut headers: Headers = vec![("Via", "a"), ("Via", "b")].into_iter().collect();
+
+        assert_eq!(headers.remove("via"), Some("a".to_string()));
+        assert!(headers.is_empty());
+        assert_eq!(headers.remove("via"), None);
+    }
+}
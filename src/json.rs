@@ -0,0 +1,123 @@
+use crate::Error;
+use serde::de::{Deserialize, DeserializeOwned};
+use serde::Serialize;
+use std::io::{self, Read};
+
+pub(crate) fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    serde_json::to_vec(value).map_err(|error| Error::Json {
+        error,
+        offset: None,
+    })
+}
+
+pub(crate) fn from_slice<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T, Error> {
+    serde_json::from_slice(bytes).map_err(|error| {
+        // The line is one-based, and 0 if the error has no position.
+        let offset = match error.line() {
+            0 => None,
+            line => {
+                // The first line starts at the start of the body.
+                let line_start = match line.checked_sub(2) {
+                    Some(newlines) => bytes
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, &b)| b == b'\n')
+                        .nth(newlines)
+                        .map_or(0, |(index, _)| index + 1),
+                    None => 0,
+                };
+                Some(line_start + error.column())
+            }
+        };
+        Error::Json { error, offset }
+    })
+}
+
+pub(crate) fn from_reader<R: Read, T: DeserializeOwned>(reader: R) -> Result<T, Error> {
+    let mut reader = PositionReader {
+        inner: reader,
+        line: 1,
+        line_start: 0,
+        position: 0,
+    };
+    serde_json::from_reader(&mut reader).map_err(|error| {
+        if error.is_io() {
+            // The errors of the reader, eg. a ResponseLazy.
+            Error::from(io::Error::from(error))
+        } else {
+            let offset = match error.line() {
+                line if line == reader.line => Some(reader.line_start + error.column()),
+                _ => None,
+            };
+            Error::Json { error, offset }
+        }
+    })
+}
+
+/// Keeps track of where the line being read started, to find the byte
+/// offset of errors from their line and column.
+struct PositionReader<R> {
+    inner: R,
+    line: usize,
+    line_start: usize,
+    position: usize,
+}
+
+impl<R: Read> Read for PositionReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = self.inner.read(buf)?;
+        for (index, _) in buf[..length]
+            .iter()
+            .enumerate()
+            .filter(|(_, &b)| b == b'\n')
+        {
+            self.line += 1;
+            self.line_start = self.position + index + 1;
+        }
+        self.position += length;
+        Ok(length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_reader, from_slice};
+    use crate::Error;
+
+    #[test]
+    fn finds_offsets_in_slices() {
+        let result: Result<serde_json::Value, Error> = from_slice(b"{\n  \"a\": 1,\n  \"b\": x\n}");
+        match result {
+            Err(Error::Json { offset, .. }) => assert_eq!(offset, Some(20)),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn finds_offsets_on_the_first_line() {
+        let result: Result<Vec<u32>, Error> = from_slice(b"[1, x]");
+        match result {
+            Err(Error::Json { offset, .. }) => assert_eq!(offset, Some(5)),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn finds_offsets_in_readers() {
+        let result: Result<serde_json::Value, Error> =
+            from_reader(&b"{\n  \"a\": 1,\n  \"b\": x\n}"[..]);
+        match result {
+            Err(Error::Json { offset, .. }) => assert_eq!(offset, Some(20)),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn has_no_offset_without_position() {
+        let map = std::collections::HashMap::from([((1, 2), 3)]);
+        match super::to_vec(&map) {
+            Err(Error::Json { offset, .. }) => assert_eq!(offset, None),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
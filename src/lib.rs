@@ -60,6 +60,99 @@
 //! If the timeout is set with `with_timeout`, the environment
 //! variable will be ignored.
 //!
+//! ## `serde`
+//!
+//! This feature adds
+//! [`Request::with_query_struct`](struct.Request.html#method.with_query_struct)
+//! and
+//! [`Request::with_form_struct`](struct.Request.html#method.with_form_struct),
+//! which turn the fields of any `serde::Serialize` struct or map into
+//! query parameters or an `application/x-www-form-urlencoded` body.
+//! `None` fields are left out, and sequences repeat their field.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), tinyget::Error> {
+//! #[derive(serde::Serialize)]
+//! struct Login<'a> {
+//!     user: &'a str,
+//!     remember: bool,
+//! }
+//!
+//! let response = tinyget::post("http://httpbin.org/post")
+//!     .with_form_struct(&Login { user: "tinyget", remember: true })?
+//!     .send()?;
+//! # Ok(()) }
+//! ```
+//!
+//! ## `json`
+//!
+//! This feature uses [`serde_json`](https://crates.io/crates/serde_json)
+//! to add [`Request::with_json`](struct.Request.html#method.with_json)
+//! for sending any `serde::Serialize` value as the body, and
+//! [`Response::json`](struct.Response.html#method.json) and
+//! [`ResponseLazy::json`](struct.ResponseLazy.html#method.json) for
+//! deserializing the body into any `serde::Deserialize` type. Errors
+//! are returned as [`Json`](enum.Error.html#variant.Json), along with
+//! the byte offset they were found at.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), tinyget::Error> {
+//! let response = tinyget::post("http://httpbin.org/anything")
+//!     .with_json(&serde_json::json!({ "name": "tinyget" }))?
+//!     .send()?;
+//! let body: serde_json::Value = response.json()?;
+//! # Ok(()) }
+//! ```
+//!
+//! ## `cbor` and `msgpack`
+//!
+//! These features add the same methods as `json` for
+//! [CBOR](https://crates.io/crates/ciborium) and
+//! [MessagePack](https://crates.io/crates/rmp-serde):
+//! [`Request::with_cbor`](struct.Request.html#method.with_cbor) and
+//! [`Request::with_msgpack`](struct.Request.html#method.with_msgpack)
+//! for the body, and [`Response::cbor`](struct.Response.html#method.cbor)
+//! and [`Response::msgpack`](struct.Response.html#method.msgpack)
+//! (and the same on `ResponseLazy`) for deserializing the response.
+//! Unlike `json`, these check the response's `Content-Type` first,
+//! and return
+//! [`UnexpectedContentType`](enum.Error.html#variant.UnexpectedContentType)
+//! if the server replied with something else, eg. an HTML error page.
+//!
+//! ## `compression`
+//!
+//! This feature uses [`flate2`](https://crates.io/crates/flate2) to
+//! send `Accept-Encoding: gzip, deflate` with requests, and to
+//! decompress response bodies with those codings as they are read,
+//! including in [`ResponseLazy`](struct.ResponseLazy.html). The
+//! `Content-Encoding` and `Content-Length` headers are removed from
+//! decompressed responses, and the compressed length is available
+//! from [`Response::wire_len`](struct.Response.html#method.wire_len).
+//! Bodies that decompress to over 100 times their size are rejected
+//! with
+//! [`DecompressionRatioTooHigh`](enum.Error.html#variant.DecompressionRatioTooHigh),
+//! see
+//! [`with_max_decompression_ratio`](struct.Request.html#method.with_max_decompression_ratio).
+//! Decompression can be turned off per request with
+//! [`with_decompression`](struct.Request.html#method.with_decompression),
+//! and is skipped for requests that set their own `Accept-Encoding`.
+//!
+//! Request bodies can be compressed with gzip or deflate too, see
+//! [`with_body_compression`](struct.Request.html#method.with_body_compression).
+//!
+//! ## `zstd`
+//!
+//! This feature enables `compression`, and adds
+//! [`Coding::Zstd`](enum.Coding.html#variant.Zstd) for compressing
+//! request bodies with [`zstd`](https://crates.io/crates/zstd).
+//!
+//! ## `charset`
+//!
+//! This feature uses [`encoding_rs`](https://crates.io/crates/encoding_rs)
+//! to let [`Response::text`](struct.Response.html#method.text) decode
+//! bodies in legacy charsets, eg. `windows-1252` or `Shift_JIS`.
+//! Without it, only UTF-8 and UTF-16 bodies can be decoded.
+//!
 //! # Examples
 //!
 //! This is a simple example of sending a GET request and printing out
@@ -97,10 +190,13 @@
 //!
 //! Reading the headers sent by the servers is done via the
 //! [`headers`](struct.Response.html#structfield.headers) field of the
-//! [`Response`](struct.Response.html). Note: the header field names
-//! (that is, the *keys* of the `HashMap`) are all lowercase: this is
-//! because the names are case-insensitive according to the spec, and
-//! this unifies the casings for easier `get()`ing.
+//! [`Response`](struct.Response.html), which is a
+//! [`Headers`](struct.Headers.html) collection. Note: the header
+//! field names are matched case-insensitively, because the names are
+//! case-insensitive according to the spec. Fields that the server
+//! sent multiple times, such as `Set-Cookie`, keep all their values
+//! in order, and can be read with
+//! [`get_all`](struct.Headers.html#method.get_all).
 //!
 //! ```
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -112,7 +208,9 @@
 //! ## Query Parameters
 //!
 //! To add query parameters to your request, use `with_query("key", "value")` before
-//! `send()`.
+//! `send()`. Parameters are sent in the order they were added, and
+//! [`append_query`](struct.Request.html#method.append_query) can be
+//! used to send the same key multiple times.
 //!
 //! ```
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -141,6 +239,22 @@
 //! # Ok(()) }
 //! ```
 //!
+//! Forms can be sent with
+//! [`with_form`](struct.Request.html#method.with_form), which encodes
+//! the fields as `application/x-www-form-urlencoded`, and responses in
+//! the same format can be parsed with
+//! [`Response::form`](struct.Response.html#method.form).
+//!
+//! Large bodies can be streamed from a reader, eg. a file, with
+//! [`with_body_reader`](struct.Request.html#method.with_body_reader),
+//! or from a file with
+//! [`with_body_file`](struct.Request.html#method.with_body_file),
+//! instead of being loaded into memory first. Files can be uploaded
+//! along with form fields in a
+//! [`Multipart`](struct.Multipart.html) body. With
+//! [`with_expect_continue`](struct.Request.html#method.with_expect_continue),
+//! the body is only sent after the server has accepted the request.
+//!
 //! ## Timeouts
 //! To avoid timing out, or limit the request's response time, use
 //! `with_timeout(n)` before `send()`. The given value is in seconds.
@@ -160,11 +274,37 @@
 #[cfg(feature = "https")]
 extern crate native_tls;
 
+mod body;
+#[cfg(feature = "cbor")]
+mod cbor;
+mod charset;
+#[cfg(feature = "compression")]
+mod compression;
 mod connection;
+mod date;
 mod error;
+mod headers;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod multipart;
+#[cfg(feature = "serde")]
+mod pairs;
 mod request;
 mod response;
+mod status;
+mod template;
+mod timings;
+mod typed_headers;
 
+#[cfg(feature = "compression")]
+pub use compression::Coding;
 pub use error::*;
+pub use headers::*;
+pub use multipart::*;
 pub use request::*;
 pub use response::*;
+pub use status::*;
+pub use timings::*;
+pub use typed_headers::*;
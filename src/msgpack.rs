@@ -0,0 +1,78 @@
+use crate::Error;
+use rmp_serde::decode;
+use serde::de::{Deserialize, DeserializeOwned};
+use serde::Serialize;
+use std::io::{self, Read};
+
+/// The `Content-Type` of MessagePack request bodies.
+pub(crate) const CONTENT_TYPE: &str = "application/msgpack";
+
+/// Returns whether `media_type`, in lowercase and without parameters,
+/// is one of the names MessagePack is sent with.
+pub(crate) fn accepts(media_type: &str) -> bool {
+    matches!(
+        media_type,
+        "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack"
+    ) || (media_type.starts_with("application/") && media_type.ends_with("+msgpack"))
+}
+
+/// Serializes `value` with structs as maps, so that the fields can be
+/// matched by name on the other end.
+pub(crate) fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    rmp_serde::to_vec_named(value).map_err(|error| Error::MsgPack(error.to_string()))
+}
+
+pub(crate) fn from_slice<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T, Error> {
+    rmp_serde::from_slice(bytes).map_err(convert_error)
+}
+
+pub(crate) fn from_reader<R: Read, T: DeserializeOwned>(reader: R) -> Result<T, Error> {
+    rmp_serde::from_read(reader).map_err(convert_error)
+}
+
+fn convert_error(error: decode::Error) -> Error {
+    match error {
+        // The errors of the reader, eg. a ResponseLazy. Running out of
+        // bytes means that the body was cut short.
+        decode::Error::InvalidMarkerRead(err) | decode::Error::InvalidDataRead(err)
+            if err.kind() != io::ErrorKind::UnexpectedEof =>
+        {
+            Error::from(err)
+        }
+        error => Error::MsgPack(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accepts, from_reader, from_slice, to_vec};
+    use crate::Error;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn round_trips() {
+        let map = BTreeMap::from([("a", 1)]);
+        let bytes = to_vec(&map).unwrap();
+        assert_eq!(bytes, [0x81, 0xa1, b'a', 0x01]);
+        let decoded: BTreeMap<&str, i32> = from_slice(&bytes).unwrap();
+        assert_eq!(decoded, map);
+        let decoded: BTreeMap<String, i32> = from_reader(&bytes[..]).unwrap();
+        assert_eq!(decoded.get("a"), Some(&1));
+    }
+
+    #[test]
+    fn reports_truncated_and_invalid_bodies() {
+        let result: Result<BTreeMap<&str, i32>, Error> = from_slice(&[0x81, 0xa1]);
+        assert!(matches!(result, Err(Error::MsgPack(_))));
+        let result: Result<String, Error> = from_slice(&[0x01]);
+        assert!(matches!(result, Err(Error::MsgPack(_))));
+    }
+
+    #[test]
+    fn accepts_known_names() {
+        assert!(accepts("application/msgpack"));
+        assert!(accepts("application/x-msgpack"));
+        assert!(accepts("application/vnd.msgpack"));
+        assert!(!accepts("application/octet-stream"));
+    }
+}
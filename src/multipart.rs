@@ -0,0 +1,319 @@
+use crate::body::{Body, BodyWriter};
+use crate::Error;
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A `multipart/form-data` request body, as described in RFC 7578,
+/// for uploading files along with form fields. Set as the body of a
+/// request with
+/// [`Request::with_multipart`](struct.Request.html#method.with_multipart).
+///
+/// The parts are separated by a random boundary. If the sizes of all
+/// the parts are known, the body is sent with a `Content-Length`,
+/// otherwise it is sent with `Transfer-Encoding: chunked`. Files are
+/// streamed from disk as the body is sent.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), tinyget::Error> {
+/// use tinyget::{Multipart, Part};
+///
+/// let form = Multipart::new()
+///     .text("name", "build 42")
+///     .file("artifact", "target/release/app.tar.gz")?
+///     .part(
+///         "notes",
+///         Part::bytes("## Notes\n").with_content_type("text/markdown"),
+///     );
+/// let response = tinyget::post("http://example.com/upload")
+///     .with_multipart(form)
+///     .send()?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct Multipart {
+    boundary: String,
+    parts: Vec<(String, Part)>,
+}
+
+impl Multipart {
+    /// Creates an empty `multipart/form-data` body with a random
+    /// boundary.
+    pub fn new() -> Multipart {
+        Multipart {
+            boundary: random_boundary(),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Returns the boundary the parts are separated with.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Adds a text field.
+    pub fn text<T: Into<String>, U: Into<String>>(self, name: T, value: U) -> Multipart {
+        self.part(name, Part::text(value))
+    }
+
+    /// Adds a field with binary contents, sent as
+    /// `application/octet-stream`.
+    pub fn bytes<T: Into<String>, U: Into<Vec<u8>>>(self, name: T, bytes: U) -> Multipart {
+        self.part(name, Part::bytes(bytes))
+    }
+
+    /// Adds a file, which is sent as `application/octet-stream` with
+    /// the name of the file at `path` as its file name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`IoError`](enum.Error.html#variant.IoError) if the
+    /// file can't be opened.
+    pub fn file<T: Into<String>, P: AsRef<Path>>(
+        self,
+        name: T,
+        path: P,
+    ) -> Result<Multipart, Error> {
+        let path = path.as_ref();
+        let mut part = Part::file(File::open(path)?);
+        if let Some(file_name) = path.file_name() {
+            part = part.with_file_name(file_name.to_string_lossy());
+        }
+        Ok(self.part(name, part))
+    }
+
+    /// Adds a part, for setting its content type or file name.
+    pub fn part<T: Into<String>>(mut self, name: T, part: Part) -> Multipart {
+        self.parts.push((name.into(), part));
+        self
+    }
+
+    /// Returns the value of the `Content-Type` header for this body.
+    pub(crate) fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Returns the length of the body, if the lengths of all the
+    /// parts are known.
+    pub(crate) fn len(&self) -> Option<u64> {
+        let mut length = self.closing_delimiter().len() as u64;
+        for (name, part) in &self.parts {
+            length += self.part_head(name, part).len() as u64 + part.body.len()? + 2;
+        }
+        Some(length)
+    }
+
+    /// Makes sure the content types of the parts can't inject extra
+    /// lines into the body.
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        for (_, part) in &self.parts {
+            if let Some(content_type) = &part.content_type {
+                if content_type.contains(['\r', '\n', '\0']) {
+                    return Err(Error::InvalidHeader("Content-Type".to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn write_contents<W: BodyWriter>(&self, stream: &mut W) -> Result<(), Error> {
+        for (name, part) in &self.parts {
+            stream.write_all(self.part_head(name, part).as_bytes())?;
+            part.body.write_contents(stream)?;
+            stream.write_all(b"\r\n")?;
+        }
+        stream.write_all(self.closing_delimiter().as_bytes())?;
+        Ok(())
+    }
+
+    /// Returns the delimiter and header fields before the contents of
+    /// `part`.
+    fn part_head(&self, name: &str, part: &Part) -> String {
+        let mut head = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            self.boundary,
+            encode_parameter(name)
+        );
+        if let Some(file_name) = &part.file_name {
+            head += &format!("; filename=\"{}\"", encode_parameter(file_name));
+        }
+        head += "\r\n";
+        if let Some(content_type) = &part.content_type {
+            head += &format!("Content-Type: {}\r\n", content_type);
+        }
+        head + "\r\n"
+    }
+
+    fn closing_delimiter(&self) -> String {
+        format!("--{}--\r\n", self.boundary)
+    }
+}
+
+impl Default for Multipart {
+    fn default() -> Multipart {
+        Multipart::new()
+    }
+}
+
+/// A part of a [`Multipart`](struct.Multipart.html) body.
+///
+/// Text parts are sent without a `Content-Type`, which means
+/// `text/plain` to the server, and other parts are sent as
+/// `application/octet-stream`, unless set otherwise with
+/// [`with_content_type`](struct.Part.html#method.with_content_type).
+#[derive(Clone, PartialEq, Debug)]
+pub struct Part {
+    body: Body,
+    content_type: Option<String>,
+    file_name: Option<String>,
+}
+
+impl Part {
+    /// Creates a text part.
+    pub fn text<T: Into<String>>(value: T) -> Part {
+        Part {
+            body: Body::Bytes(value.into().into_bytes()),
+            content_type: None,
+            file_name: None,
+        }
+    }
+
+    /// Creates a part with binary contents.
+    pub fn bytes<T: Into<Vec<u8>>>(bytes: T) -> Part {
+        Part::with_body(Body::Bytes(bytes.into()))
+    }
+
+    /// Creates a part that is sent from `file`, from its start. The
+    /// file is read as the body is sent.
+    pub fn file(file: File) -> Part {
+        Part::with_body(Body::from_file(file))
+    }
+
+    /// Creates a part that is streamed from `reader`. If the `length`
+    /// is not known, the whole body is sent with
+    /// `Transfer-Encoding: chunked`. See
+    /// [`Request::with_body_reader`](struct.Request.html#method.with_body_reader)
+    /// for more.
+    pub fn reader<R: Read + Send + 'static>(reader: R, length: Option<u64>) -> Part {
+        Part::with_body(Body::from_reader(reader, length))
+    }
+
+    fn with_body(body: Body) -> Part {
+        Part {
+            body,
+            content_type: Some("application/octet-stream".to_string()),
+            file_name: None,
+        }
+    }
+
+    /// Sets the `Content-Type` of the part.
+    pub fn with_content_type<T: Into<String>>(mut self, content_type: T) -> Part {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Sets the file name of the part. Characters outside of ASCII
+    /// are percent-encoded, as recommended by RFC 7578.
+    pub fn with_file_name<T: Into<String>>(mut self, file_name: T) -> Part {
+        self.file_name = Some(file_name.into());
+        self
+    }
+}
+
+/// Percent-encodes the characters of a `name` or `filename` parameter
+/// that are not printable ASCII, along with `"`, `%` and `\`, which
+/// would break the quoting or the encoding.
+fn encode_parameter(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for &b in value.as_bytes() {
+        if b == b' ' || (b.is_ascii_graphic() && !matches!(b, b'"' | b'%' | b'\\')) {
+            encoded.push(b as char);
+        } else {
+            encoded += &format!("%{:02X}", b);
+        }
+    }
+    encoded
+}
+
+/// Returns a boundary that is very unlikely to appear in the parts.
+fn random_boundary() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    // RandomState is seeded randomly, and the counter and the time
+    // make sure that the boundaries differ even if the seed doesn't.
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_usize(COUNTER.fetch_add(1, Ordering::Relaxed));
+    if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    let first = hasher.finish();
+    hasher.write_u64(first);
+    format!("tinyget-{:016x}{:016x}", first, hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Multipart, Part};
+    use crate::Error;
+
+    fn multipart() -> Multipart {
+        Multipart {
+            boundary: "b".to_string(),
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn writes_parts() {
+        let form = multipart().text("name", "build 42").part(
+            "notes",
+            Part::bytes("# Notes")
+                .with_content_type("text/markdown")
+                .with_file_name("notes.md"),
+        );
+        let mut written = Vec::new();
+        form.write_contents(&mut written).unwrap();
+        assert_eq!(
+            String::from_utf8(written.clone()).unwrap(),
+            "--b\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\nbuild 42\r\n\
+             --b\r\nContent-Disposition: form-data; name=\"notes\"; filename=\"notes.md\"\r\n\
+             Content-Type: text/markdown\r\n\r\n# Notes\r\n--b--\r\n"
+        );
+        assert_eq!(form.len(), Some(written.len() as u64));
+    }
+
+    #[test]
+    fn encodes_names() {
+        let form = multipart().part("fïle", Part::bytes("").with_file_name("\"résumé\".pdf"));
+        let mut written = Vec::new();
+        form.write_contents(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.contains("name=\"f%C3%AFle\"; filename=\"%22r%C3%A9sum%C3%A9%22.pdf\""));
+    }
+
+    #[test]
+    fn length_is_unknown_with_unknown_parts() {
+        let form = multipart()
+            .text("a", "b")
+            .part("c", Part::reader(&b"d"[..], None));
+        assert_eq!(form.len(), None);
+        let form = multipart().part("c", Part::reader(&b"d"[..], Some(1)));
+        assert!(form.len().is_some());
+    }
+
+    #[test]
+    fn rejects_content_type_injection() {
+        let form = multipart().part("a", Part::text("b").with_content_type("text/plain\r\nX: y"));
+        assert!(matches!(form.validate(), Err(Error::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn boundaries_are_random() {
+        assert_ne!(Multipart::new().boundary(), Multipart::new().boundary());
+    }
+}
@@ -0,0 +1,569 @@
+use crate::Error;
+use serde::ser::{self, Impossible, Serialize};
+use std::fmt;
+
+/// Serializes a struct or a map into `(key, value)` pairs, for query
+/// parameters and forms.
+///
+/// The pairs are in the order the fields are serialized in, which is
+/// the declaration order for structs. `None` and `()` values are
+/// omitted, sequences become one pair per element, booleans and
+/// numbers become their `Display` forms, and unit enum variants
+/// become their names. Nested structs and maps, and enum variants with
+/// data, are not supported.
+pub(crate) fn to_pairs<T: Serialize + ?Sized>(value: &T) -> Result<Vec<(String, String)>, Error> {
+    let mut pairs = Vec::new();
+    match value.serialize(PairsSerializer { pairs: &mut pairs }) {
+        Ok(()) => Ok(pairs),
+        Err(SerializeError(message)) => Err(Error::SerializePairs(message)),
+    }
+}
+
+#[derive(Debug)]
+struct SerializeError(String);
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+impl ser::Error for SerializeError {
+    fn custom<T: fmt::Display>(msg: T) -> SerializeError {
+        SerializeError(msg.to_string())
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T, SerializeError> {
+    Err(SerializeError(format!(
+        "{} can't be serialized into key-value pairs",
+        what
+    )))
+}
+
+/// Serializes the top level value, which must be a struct or a map.
+struct PairsSerializer<'a> {
+    pairs: &'a mut Vec<(String, String)>,
+}
+
+const TOP_LEVEL: &str = "values other than structs and maps";
+
+impl<'a> ser::Serializer for PairsSerializer<'a> {
+    type Ok = ();
+    type Error = SerializeError;
+    type SerializeSeq = Impossible<(), SerializeError>;
+    type SerializeTuple = Impossible<(), SerializeError>;
+    type SerializeTupleStruct = Impossible<(), SerializeError>;
+    type SerializeTupleVariant = Impossible<(), SerializeError>;
+    type SerializeMap = MapSerializer<'a>;
+    type SerializeStruct = MapSerializer<'a>;
+    type SerializeStructVariant = Impossible<(), SerializeError>;
+
+    fn serialize_bool(self, _: bool) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_i8(self, _: i8) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_i16(self, _: i16) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_i32(self, _: i32) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_i64(self, _: i64) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_u8(self, _: u8) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_u16(self, _: u16) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_u32(self, _: u32) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_u64(self, _: u64) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_f32(self, _: f32) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_f64(self, _: f64) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_char(self, _: char) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_str(self, _: &str) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_none(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), SerializeError> {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<MapSerializer<'a>, SerializeError> {
+        Ok(MapSerializer {
+            pairs: self.pairs,
+            key: None,
+        })
+    }
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<MapSerializer<'a>, SerializeError> {
+        self.serialize_map(None)
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        unsupported(TOP_LEVEL)
+    }
+}
+
+/// Serializes the fields of the top level struct or map into pairs.
+struct MapSerializer<'a> {
+    pairs: &'a mut Vec<(String, String)>,
+    key: Option<String>,
+}
+
+impl MapSerializer<'_> {
+    fn push<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), SerializeError> {
+        let mut values = Vec::new();
+        value.serialize(ValueSerializer {
+            values: &mut values,
+            in_seq: false,
+        })?;
+        self.pairs
+            .extend(values.into_iter().map(|value| (key.to_string(), value)));
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for MapSerializer<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        let mut keys = Vec::new();
+        key.serialize(ValueSerializer {
+            values: &mut keys,
+            in_seq: true,
+        })?;
+        match keys.pop() {
+            Some(key) if keys.is_empty() => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => unsupported("keys other than strings, numbers and unit variants"),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        match self.key.take() {
+            Some(key) => self.push(&key, value),
+            None => Err(SerializeError(
+                "value serialized before its key".to_string(),
+            )),
+        }
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+}
+
+/// Serializes a field's value into zero or more strings: none for
+/// `None` and `()`, and one for each element of sequences.
+struct ValueSerializer<'a> {
+    values: &'a mut Vec<String>,
+    in_seq: bool,
+}
+
+impl ValueSerializer<'_> {
+    fn push<T: ToString>(self, value: T) -> Result<(), SerializeError> {
+        self.values.push(value.to_string());
+        Ok(())
+    }
+}
+
+const NESTED: &str = "nested structs and maps";
+const VARIANTS: &str = "enum variants with data";
+
+impl<'a> ser::Serializer for ValueSerializer<'a> {
+    type Ok = ();
+    type Error = SerializeError;
+    type SerializeSeq = SeqSerializer<'a>;
+    type SerializeTuple = SeqSerializer<'a>;
+    type SerializeTupleStruct = SeqSerializer<'a>;
+    type SerializeTupleVariant = Impossible<(), SerializeError>;
+    type SerializeMap = Impossible<(), SerializeError>;
+    type SerializeStruct = Impossible<(), SerializeError>;
+    type SerializeStructVariant = Impossible<(), SerializeError>;
+
+    fn serialize_bool(self, value: bool) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_i8(self, value: i8) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_i16(self, value: i16) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_i32(self, value: i32) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_i64(self, value: i64) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_i128(self, value: i128) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_u8(self, value: u8) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_u16(self, value: u16) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_u32(self, value: u32) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_u64(self, value: u64) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_u128(self, value: u128) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_f32(self, value: f32) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_f64(self, value: f64) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_char(self, value: char) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_str(self, value: &str) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), SerializeError> {
+        match std::str::from_utf8(value) {
+            Ok(value) => self.push(value),
+            Err(_) => unsupported("bytes that are not UTF-8"),
+        }
+    }
+    fn serialize_none(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), SerializeError> {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<(), SerializeError> {
+        self.push(variant)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), SerializeError> {
+        unsupported(VARIANTS)
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<SeqSerializer<'a>, SerializeError> {
+        if self.in_seq {
+            return unsupported("nested sequences");
+        }
+        Ok(SeqSerializer {
+            values: self.values,
+        })
+    }
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer<'a>, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer<'a>, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        unsupported(VARIANTS)
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, SerializeError> {
+        unsupported(NESTED)
+    }
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, SerializeError> {
+        unsupported(NESTED)
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        unsupported(VARIANTS)
+    }
+}
+
+/// Serializes the elements of a sequence value, one string each.
+struct SeqSerializer<'a> {
+    values: &'a mut Vec<String>,
+}
+
+impl SeqSerializer<'_> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(ValueSerializer {
+            values: self.values,
+            in_seq: true,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_pairs;
+    use crate::Error;
+    use serde::ser::{Serialize, SerializeStruct, Serializer};
+    use std::collections::BTreeMap;
+
+    enum Order {
+        Ascending,
+    }
+
+    impl Serialize for Order {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self {
+                Order::Ascending => serializer.serialize_unit_variant("Order", 0, "asc"),
+            }
+        }
+    }
+
+    struct Search {
+        q: &'static str,
+        page: Option<Option<u32>>,
+        limit: Option<u32>,
+        tags: Vec<&'static str>,
+        exact: bool,
+        order: Order,
+    }
+
+    // Written out by hand, as the derive macros are not a dependency.
+    impl Serialize for Search {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut s = serializer.serialize_struct("Search", 6)?;
+            s.serialize_field("q", &self.q)?;
+            s.serialize_field("page", &self.page)?;
+            s.serialize_field("limit", &self.limit)?;
+            s.serialize_field("tags", &self.tags)?;
+            s.serialize_field("exact", &self.exact)?;
+            s.serialize_field("order", &self.order)?;
+            s.end()
+        }
+    }
+
+    #[test]
+    fn serializes_structs_in_order() {
+        let search = Search {
+            q: "rust http",
+            page: Some(None),
+            limit: Some(10),
+            tags: vec!["a", "b"],
+            exact: false,
+            order: Order::Ascending,
+        };
+        let pairs = to_pairs(&search).unwrap();
+        let pairs: Vec<(&str, &str)> = pairs.iter().map(|(k, v)| (&k[..], &v[..])).collect();
+        assert_eq!(
+            pairs,
+            [
+                ("q", "rust http"),
+                ("limit", "10"),
+                ("tags", "a"),
+                ("tags", "b"),
+                ("exact", "false"),
+                ("order", "asc"),
+            ]
+        );
+    }
+
+    #[test]
+    fn serializes_maps() {
+        let map = BTreeMap::from([(2, "b"), (1, "a")]);
+        let pairs = to_pairs(&map).unwrap();
+        assert_eq!(
+            pairs,
+            [
+                ("1".to_string(), "a".to_string()),
+                ("2".to_string(), "b".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_values() {
+        let nested = BTreeMap::from([("a", BTreeMap::from([("b", "c")]))]);
+        assert!(matches!(to_pairs(&nested), Err(Error::SerializePairs(_))));
+        assert!(matches!(to_pairs(&"a"), Err(Error::SerializePairs(_))));
+        assert!(matches!(
+            to_pairs(&BTreeMap::from([("a", vec![vec![1]])])),
+            Err(Error::SerializePairs(_))
+        ));
+    }
+}
@@ -1,11 +1,43 @@
+use crate::body::{Body, BodyWriter};
 use crate::connection::Connection;
-use crate::{Error, Response, ResponseLazy};
-use std::collections::HashMap;
+use crate::response::ParseOptions;
+use crate::template;
+#[cfg(feature = "compression")]
+use crate::Coding;
+use crate::{CacheControl, ContentType, ETag};
+use crate::{Error, Headers, Multipart, ParseMode, Response, ResponseLazy};
+use std::fs::File;
+use std::io::Read;
+use std::time::{Duration, SystemTime};
 
 /// A URL type for requests.
 #[allow(clippy::upper_case_acronyms)]
 pub type URL = String;
 
+/// How spaces are encoded in query parameters. Set with
+/// [`Request::with_query_encoding`](struct.Request.html#method.with_query_encoding).
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum QueryEncoding {
+    /// Spaces are encoded as `%20`, like every other reserved
+    /// character. This is the default.
+    #[default]
+    Percent,
+    /// Spaces are encoded as `+`, like in
+    /// `application/x-www-form-urlencoded` HTML forms.
+    Form,
+}
+
+/// A query parameter of a [`Request`](struct.Request.html).
+#[derive(Clone, PartialEq, Debug)]
+struct QueryParam {
+    key: String,
+    value: String,
+    /// The parameter as written in the url passed to
+    /// [`Request::new`](struct.Request.html#method.new), which is sent
+    /// as-is, instead of re-encoding `key` and `value`.
+    raw: Option<String>,
+}
+
 /// An HTTP request.
 ///
 /// Generally created by the [`tinyget::get`](fn.get.html)-style
@@ -26,12 +58,18 @@ pub struct Request {
     pub(crate) host: URL,
     resource: URL,
     method: String,
-    body: Vec<u8>,
-    headers: HashMap<String, String>,
-    query: HashMap<String, String>,
+    body: Body,
+    headers: Headers,
+    trailers: Headers,
+    query: Vec<QueryParam>,
+    query_encoding: QueryEncoding,
     #[cfg(feature = "timeout")]
     pub(crate) timeout: Option<u64>,
     max_redirects: usize,
+    pub(crate) expect_continue: Option<Duration>,
+    #[cfg(feature = "compression")]
+    body_compression: Option<Coding>,
+    pub(crate) parse_options: ParseOptions,
     https: bool,
     #[cfg(feature = "https")]
     pub(crate) danger_accept_invalid_certs: bool,
@@ -47,16 +85,23 @@ impl Request {
     /// sending the request, see [`send`](struct.Request.html#method.send).
     pub fn new<T: Into<URL>>(url: T) -> Request {
         let (https, host, resource) = parse_url(url.into());
+        let (resource, query) = split_query(resource);
         Request {
             host,
             resource,
             method: "GET".to_string(),
-            body: Vec::new(),
-            headers: HashMap::new(),
-            query: HashMap::new(),
+            body: Body::default(),
+            headers: Headers::new(),
+            trailers: Headers::new(),
+            query,
+            query_encoding: QueryEncoding::default(),
             #[cfg(feature = "timeout")]
             timeout: None,
             max_redirects: 100,
+            expect_continue: None,
+            #[cfg(feature = "compression")]
+            body_compression: None,
+            parse_options: ParseOptions::default(),
             https,
             #[cfg(feature = "https")]
             danger_accept_invalid_certs: false,
@@ -66,13 +111,141 @@ impl Request {
         }
     }
 
+    /// Creates a new HTTP `Request` from a URI template, substituting
+    /// the variables in it with the given values.
+    ///
+    /// Variables are written as `{name}`, and their values are
+    /// percent-encoded so that they can't escape their path segment
+    /// or query component, eg. a `/` becomes `%2F`. `{+name}` leaves
+    /// reserved characters like `/` and `?` as-is, for inserting
+    /// whole paths, and `{#name}` does the same but prefixes the value
+    /// with `#`. Variables without a value expand to nothing. These
+    /// are the level 1 and 2 expressions of RFC 6570.
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`MalformedTemplate`](enum.Error.html#variant.MalformedTemplate)
+    /// if the template has unbalanced braces, empty expressions, or
+    /// expressions from the higher levels of RFC 6570.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// let request = tinyget::Request::from_template(
+    ///     "https://example.com/repos/{owner}/{repo}/issues/{n}",
+    ///     [("owner", "justjavac"), ("repo", "rust_tinyget"), ("n", "1")],
+    /// )?;
+    /// # Ok(()) }
+    /// ```
+    pub fn from_template<I, K, V>(template: &str, vars: I) -> Result<Request, Error>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let vars: Vec<(String, String)> = vars
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect();
+        Ok(Request::new(template::expand(template, &vars)?))
+    }
+
     /// Adds a header to the request this is called on. Use this
     /// function to add headers to your requests.
-    pub fn with_header<T: Into<String>, U: Into<String>>(mut self, key: T, value: U) -> Request {
-        self.headers.insert(key.into(), value.into());
+    ///
+    /// This replaces any previous values of the header, like
+    /// [`set_header`](struct.Request.html#method.set_header). To send
+    /// a header multiple times, use
+    /// [`append_header`](struct.Request.html#method.append_header).
+    pub fn with_header<T: Into<String>, U: Into<String>>(self, key: T, value: U) -> Request {
+        self.set_header(key, value)
+    }
+
+    /// Adds a header to the end of the request's headers, keeping any
+    /// previous values of it, so the header is sent once for each
+    /// value.
+    ///
+    /// Headers are sent in the order they were added, with the name
+    /// in the casing given here.
+    pub fn append_header<T: Into<String>, U: Into<String>>(mut self, key: T, value: U) -> Request {
+        self.headers.append(key, value);
+        self
+    }
+
+    /// Sets a header, replacing all of its previous values. The
+    /// header keeps the position of its first occurrence, if it had
+    /// been added before. The name is matched case-insensitively.
+    pub fn set_header<T: Into<String>, U: Into<String>>(mut self, key: T, value: U) -> Request {
+        self.headers.insert(key, value);
         self
     }
 
+    /// Removes all the values of a header. The name is matched
+    /// case-insensitively.
+    pub fn remove_header(mut self, key: &str) -> Request {
+        self.headers.remove(key);
+        self
+    }
+
+    /// Sets the `Content-Type` header.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// use tinyget::ContentType;
+    ///
+    /// let content_type = ContentType::new("text/csv").with_param("charset", "utf-8");
+    /// let response = tinyget::post("http://example.com/import")
+    ///     .with_content_type(content_type)
+    ///     .with_body("id,name\n1,tinyget\n")
+    ///     .send()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_content_type(self, content_type: ContentType) -> Request {
+        self.set_header("Content-Type", content_type.to_string())
+    }
+
+    /// Sets the `Cache-Control` header, eg. to ask caches on the way
+    /// for a fresh response with `no-cache`.
+    pub fn with_cache_control(self, cache_control: CacheControl) -> Request {
+        self.set_header("Cache-Control", cache_control.to_string())
+    }
+
+    /// Sets the `If-None-Match` header to `etags`, so that the server
+    /// responds with `304 Not Modified` if the resource still has one
+    /// of them, eg. the [`ETag`](struct.Response.html#method.etag) of
+    /// an earlier response. Use
+    /// [`with_header`](struct.Request.html#method.with_header) to send
+    /// `If-None-Match: *`.
+    pub fn with_if_none_match<I: IntoIterator<Item = ETag>>(self, etags: I) -> Request {
+        let etags: Vec<String> = etags.into_iter().map(|etag| etag.to_string()).collect();
+        self.set_header("If-None-Match", etags.join(", "))
+    }
+
+    /// Sets the `If-Modified-Since` header, so that the server
+    /// responds with `304 Not Modified` if the resource has not
+    /// changed since `time`, eg. the
+    /// [`Last-Modified`](struct.Response.html#method.last_modified)
+    /// time of an earlier response.
+    pub fn with_if_modified_since(self, time: SystemTime) -> Request {
+        self.set_header("If-Modified-Since", crate::date::format(time))
+    }
+
+    /// Sets the `Range` header, to request the bytes from `first` to
+    /// `last`, inclusive, or to the end if `last` is `None`. Servers
+    /// that support it respond with `206 Partial Content` and a
+    /// [`Content-Range`](struct.Response.html#method.content_range).
+    pub fn with_range(self, first: u64, last: Option<u64>) -> Request {
+        let range = match last {
+            Some(last) => format!("bytes={}-{}", first, last),
+            None => format!("bytes={}-", first),
+        };
+        self.set_header("Range", range)
+    }
+
     /// Sets the HTTP method for the request.
     pub fn with_method<T: Into<String>>(mut self, method: T) -> Request {
         self.method = method.into();
@@ -81,13 +254,379 @@ impl Request {
 
     /// Sets the request body.
     pub fn with_body<T: AsRef<[u8]>>(mut self, body: T) -> Request {
-        self.body = body.as_ref().to_vec();
+        self.body = Body::Bytes(body.as_ref().to_vec());
+        self
+    }
+
+    /// Sets the request body to `value` serialized as JSON, and the
+    /// `Content-Type` to `application/json`, unless it has already
+    /// been set.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`Json`](enum.Error.html#variant.Json) error if
+    /// `value` can't be serialized, eg. if it is a map with keys that
+    /// are not strings.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// let body = std::collections::HashMap::from([("name", "tinyget")]);
+    /// let response = tinyget::post("http://example.com/users")
+    ///     .with_json(&body)?
+    ///     .send()?;
+    /// # Ok(()) }
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn with_json<T: serde::Serialize + ?Sized>(mut self, value: &T) -> Result<Request, Error> {
+        self.body = Body::Bytes(crate::json::to_vec(value)?);
+        if !self.headers.contains_key("content-type") {
+            self.headers.insert("Content-Type", "application/json");
+        }
+        Ok(self)
+    }
+
+    /// Sets the request body to `value` serialized as CBOR, and the
+    /// `Content-Type` to `application/cbor`, unless it has already
+    /// been set.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`Cbor`](enum.Error.html#variant.Cbor) error if
+    /// `value` can't be serialized.
+    #[cfg(feature = "cbor")]
+    pub fn with_cbor<T: serde::Serialize + ?Sized>(mut self, value: &T) -> Result<Request, Error> {
+        self.body = Body::Bytes(crate::cbor::to_vec(value)?);
+        if !self.headers.contains_key("content-type") {
+            self.headers
+                .insert("Content-Type", crate::cbor::CONTENT_TYPE);
+        }
+        Ok(self)
+    }
+
+    /// Sets the request body to `value` serialized as MessagePack, and
+    /// the `Content-Type` to `application/msgpack`, unless it has
+    /// already been set. Structs are serialized as maps, with their
+    /// field names.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MsgPack`](enum.Error.html#variant.MsgPack) error if
+    /// `value` can't be serialized.
+    #[cfg(feature = "msgpack")]
+    pub fn with_msgpack<T: serde::Serialize + ?Sized>(
+        mut self,
+        value: &T,
+    ) -> Result<Request, Error> {
+        self.body = Body::Bytes(crate::msgpack::to_vec(value)?);
+        if !self.headers.contains_key("content-type") {
+            self.headers
+                .insert("Content-Type", crate::msgpack::CONTENT_TYPE);
+        }
+        Ok(self)
+    }
+
+    /// Sets the request body to the `(name, value)` pairs encoded as
+    /// `application/x-www-form-urlencoded`, in order, like an HTML
+    /// form would send them. Also sets the `Content-Type` to match,
+    /// unless it has already been set.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// let response = tinyget::post("http://example.com/login")
+    ///     .with_form([("user", "tinyget"), ("password", "hunter2")])
+    ///     .send()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_form<I, T, U>(mut self, pairs: I) -> Request
+    where
+        I: IntoIterator<Item = (T, U)>,
+        T: AsRef<str>,
+        U: AsRef<str>,
+    {
+        let mut body = String::new();
+        for (name, value) in pairs {
+            if !body.is_empty() {
+                body.push('&');
+            }
+            body += &encode_query_component(name.as_ref(), QueryEncoding::Form);
+            body.push('=');
+            body += &encode_query_component(value.as_ref(), QueryEncoding::Form);
+        }
+        if !self.headers.contains_key("content-type") {
+            self.headers
+                .insert("Content-Type", "application/x-www-form-urlencoded");
+        }
+        self.body = Body::Bytes(body.into_bytes());
+        self
+    }
+
+    /// Sets the request body to the fields of `value` encoded as
+    /// `application/x-www-form-urlencoded`, like
+    /// [`with_form`](struct.Request.html#method.with_form). See
+    /// [`with_query_struct`](struct.Request.html#method.with_query_struct)
+    /// for how the fields are turned into pairs.
+    ///
+    /// # Errors
+    ///
+    /// Returns a
+    /// [`SerializePairs`](enum.Error.html#variant.SerializePairs)
+    /// error if `value` can't be turned into pairs.
+    #[cfg(feature = "serde")]
+    pub fn with_form_struct<T: serde::Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<Request, Error> {
+        Ok(self.with_form(crate::pairs::to_pairs(value)?))
+    }
+
+    /// Sets the request body to be streamed from `reader`, instead of
+    /// being loaded into memory first.
+    ///
+    /// If the `length` of the body is known, it is sent as the
+    /// `Content-Length`, and exactly that many bytes are read from
+    /// `reader`. Otherwise, the body is sent with
+    /// `Transfer-Encoding: chunked` until `reader` runs out, followed
+    /// by the fields added with
+    /// [`with_trailer`](struct.Request.html#method.with_trailer), and
+    /// a `Content-Length` header set by the caller is not sent, as
+    /// the two can't be combined.
+    ///
+    /// The reader is only read once: if the request is redirected,
+    /// or a clone of it is sent after it, sending the body again
+    /// returns a
+    /// [`BodyAlreadySent`](enum.Error.html#variant.BodyAlreadySent)
+    /// error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// let file = std::fs::File::open("upload.bin")?;
+    /// let length = file.metadata()?.len();
+    /// let response = tinyget::post("http://example.com/upload")
+    ///     .with_body_reader(file, Some(length))
+    ///     .send()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_body_reader<R: Read + Send + 'static>(
+        mut self,
+        reader: R,
+        length: Option<u64>,
+    ) -> Request {
+        self.body = Body::from_reader(reader, length);
+        self
+    }
+
+    /// Sets the request body to be sent from `file`, with the file's
+    /// length as the `Content-Length`. The whole file is sent, from
+    /// its start.
+    ///
+    /// On Linux, plain HTTP requests send the file with sendfile(2),
+    /// so the bytes are moved from the file to the connection by the
+    /// kernel. Otherwise, the file is copied through a buffer. If the
+    /// file's length can't be read, it is sent with
+    /// `Transfer-Encoding: chunked` instead.
+    ///
+    /// Unlike [`with_body_reader`](struct.Request.html#method.with_body_reader),
+    /// the file can be sent again if the request is redirected.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// let file = std::fs::File::open("artifact.tar.gz")?;
+    /// let response = tinyget::post("http://example.com/upload")
+    ///     .with_body_file(file)
+    ///     .send()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_body_file(mut self, file: File) -> Request {
+        self.body = Body::from_file(file);
+        self
+    }
+
+    /// Sets the request body to a `multipart/form-data` body, and the
+    /// `Content-Type` to match it, unless it has already been set.
+    /// See [`Multipart`](struct.Multipart.html) for an example.
+    pub fn with_multipart(mut self, multipart: Multipart) -> Request {
+        if !self.headers.contains_key("content-type") {
+            self.headers
+                .insert("Content-Type", multipart.content_type());
+        }
+        self.body = Body::Multipart(multipart);
+        self
+    }
+
+    /// Compresses the request body with `coding` when it is sent, and
+    /// sets the `Content-Encoding` to match. If the `Content-Encoding`
+    /// has already been set, eg. for a body that is already
+    /// compressed, `coding` is added to the end of it, as codings are
+    /// listed in the order they were applied. A `Content-Length`
+    /// header set with
+    /// [`with_header`](struct.Request.html#method.with_header) is
+    /// replaced, as it would be the uncompressed length.
+    ///
+    /// Bodies in memory, eg. set with
+    /// [`with_body`](struct.Request.html#method.with_body), are
+    /// compressed once before sending, and sent with the compressed
+    /// length as the `Content-Length`. Streamed bodies, eg. set with
+    /// [`with_body_reader`](struct.Request.html#method.with_body_reader),
+    /// are compressed on the fly, and sent with
+    /// `Transfer-Encoding: chunked`, as their compressed length is not
+    /// known in advance. Empty bodies are not compressed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// # let batch = "[]";
+    /// let response = tinyget::post("http://example.com/telemetry")
+    ///     .with_header("Content-Type", "application/json")
+    ///     .with_body(batch)
+    ///     .with_body_compression(tinyget::Coding::Gzip)
+    ///     .send()?;
+    /// # Ok(()) }
+    /// ```
+    #[cfg(feature = "compression")]
+    pub fn with_body_compression(mut self, coding: Coding) -> Request {
+        self.body_compression = Some(coding);
+        self
+    }
+
+    /// Adds a trailer field, which is sent after a body of unknown
+    /// length set with
+    /// [`with_body_reader`](struct.Request.html#method.with_body_reader)
+    /// or [`with_body_file`](struct.Request.html#method.with_body_file).
+    /// The names of the trailer fields are announced in the `Trailer`
+    /// header. Trailers are not sent with other bodies.
+    pub fn with_trailer<T: Into<String>, U: Into<String>>(mut self, key: T, value: U) -> Request {
+        self.trailers.append(key, value);
         self
     }
 
     /// Adds a query parameter to the URL.
-    pub fn with_query<T: Into<String>, U: Into<String>>(mut self, key: T, value: U) -> Request {
-        self.query.insert(key.into(), value.into());
+    ///
+    /// This replaces any previous values of the parameter, including
+    /// ones that were part of the url passed to
+    /// [`Request::new`](struct.Request.html#method.new), like
+    /// [`set_query`](struct.Request.html#method.set_query). To send a
+    /// parameter multiple times, use
+    /// [`append_query`](struct.Request.html#method.append_query).
+    pub fn with_query<T: Into<String>, U: Into<String>>(self, key: T, value: U) -> Request {
+        self.set_query(key, value)
+    }
+
+    /// Adds a query parameter to the end of the URL's query, keeping
+    /// any previous values of it, eg. `?tag=a&tag=b`.
+    ///
+    /// Parameters are sent in the order they were added, after the
+    /// ones that were part of the url passed to
+    /// [`Request::new`](struct.Request.html#method.new).
+    pub fn append_query<T: Into<String>, U: Into<String>>(mut self, key: T, value: U) -> Request {
+        self.query.push(QueryParam {
+            key: key.into(),
+            value: value.into(),
+            raw: None,
+        });
+        self
+    }
+
+    /// Adds each of the query parameters to the end of the URL's
+    /// query, in order, like
+    /// [`append_query`](struct.Request.html#method.append_query).
+    pub fn with_queries<I, T, U>(self, queries: I) -> Request
+    where
+        I: IntoIterator<Item = (T, U)>,
+        T: Into<String>,
+        U: Into<String>,
+    {
+        queries.into_iter().fold(self, |request, (key, value)| {
+            request.append_query(key, value)
+        })
+    }
+
+    /// Adds the fields of `value`, a struct or a map, to the end of
+    /// the URL's query, like
+    /// [`with_queries`](struct.Request.html#method.with_queries).
+    ///
+    /// Struct fields are added in the order they are declared in, so
+    /// the request line is always the same for the same value.
+    /// Fields that are `None` (at any level of nesting) or `()` are
+    /// left out, sequences add the field once for each element,
+    /// booleans and numbers are written out as text, and unit enum
+    /// variants as their (possibly renamed) names.
+    ///
+    /// # Errors
+    ///
+    /// Returns a
+    /// [`SerializePairs`](enum.Error.html#variant.SerializePairs)
+    /// error if `value` is not a struct or a map, or has fields that
+    /// are structs, maps, nested sequences or enum variants with data.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// #[derive(serde::Serialize)]
+    /// struct Search {
+    ///     q: &'static str,
+    ///     page: Option<u32>,
+    ///     tags: Vec<&'static str>,
+    /// }
+    ///
+    /// // Sends GET /search?q=tinyget&tags=http&tags=client
+    /// let search = Search { q: "tinyget", page: None, tags: vec!["http", "client"] };
+    /// let response = tinyget::get("http://example.com/search")
+    ///     .with_query_struct(&search)?
+    ///     .send()?;
+    /// # Ok(()) }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn with_query_struct<T: serde::Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<Request, Error> {
+        Ok(self.with_queries(crate::pairs::to_pairs(value)?))
+    }
+
+    /// Sets a query parameter, replacing all of its previous values.
+    /// The parameter keeps the position of its first occurrence, if
+    /// it had been added before.
+    pub fn set_query<T: Into<String>, U: Into<String>>(mut self, key: T, value: U) -> Request {
+        let key = key.into();
+        let param = QueryParam {
+            value: value.into(),
+            raw: None,
+            key,
+        };
+        match self.query.iter().position(|p| p.key == param.key) {
+            Some(index) => {
+                self.query.retain(|p| p.key != param.key);
+                self.query.insert(index, param);
+            }
+            None => self.query.push(param),
+        }
+        self
+    }
+
+    /// Removes all the values of a query parameter, including ones
+    /// that were part of the url passed to
+    /// [`Request::new`](struct.Request.html#method.new).
+    pub fn remove_query(mut self, key: &str) -> Request {
+        self.query.retain(|p| p.key != key);
+        self
+    }
+
+    /// Sets how spaces are encoded in the query parameters added with
+    /// the `*_query` functions.
+    /// [`QueryEncoding::Percent`](enum.QueryEncoding.html#variant.Percent)
+    /// by default.
+    pub fn with_query_encoding(mut self, encoding: QueryEncoding) -> Request {
+        self.query_encoding = encoding;
         self
     }
 
@@ -110,6 +649,135 @@ impl Request {
         self
     }
 
+    /// Sets how strictly the response's head is parsed. See
+    /// [`ParseMode`](enum.ParseMode.html) for the differences.
+    /// [`ParseMode::Lenient`](enum.ParseMode.html#variant.Lenient) by
+    /// default.
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Request {
+        self.parse_options.mode = mode;
+        self
+    }
+
+    /// Sets the maximum length of a single line in the response's
+    /// head, ie. the status line, a header field, or a chunk size
+    /// line, not counting the trailing `\r\n`. Unlimited by default.
+    ///
+    /// If a longer line is received,
+    /// [`LineTooLong`](enum.Error.html#variant.LineTooLong) is
+    /// returned.
+    pub fn with_max_line_length(mut self, max_line_length: usize) -> Request {
+        self.parse_options.max_line_length = Some(max_line_length);
+        self
+    }
+
+    /// Sets the maximum amount of header fields in the response, and
+    /// separately, in its trailers. Unlimited by default.
+    ///
+    /// If more fields are received,
+    /// [`TooManyHeaders`](enum.Error.html#variant.TooManyHeaders) is
+    /// returned.
+    pub fn with_max_header_count(mut self, max_header_count: usize) -> Request {
+        self.parse_options.max_header_count = Some(max_header_count);
+        self
+    }
+
+    /// Sets the maximum total size of the response's header fields in
+    /// bytes, and separately, of its trailers. Unlimited by default.
+    ///
+    /// If more bytes are received,
+    /// [`HeadersTooLarge`](enum.Error.html#variant.HeadersTooLarge)
+    /// is returned.
+    pub fn with_max_headers_size(mut self, max_headers_size: usize) -> Request {
+        self.parse_options.max_headers_size = Some(max_headers_size);
+        self
+    }
+
+    /// Sends the request with `Expect: 100-continue`, so that the body
+    /// is only sent after the server has accepted the request's head.
+    ///
+    /// After sending the head, tinyget waits at most `timeout` for the
+    /// server to respond with `100 Continue`, and then sends
+    /// the body. If the server responds with a final status instead,
+    /// eg. `401 Unauthorized`, that response is returned without
+    /// sending the body. If the server doesn't respond in time, the
+    /// body is sent anyway, as not all servers support the
+    /// expectation. If the server responds with
+    /// `417 Expectation Failed`, the request is sent again without it,
+    /// and without any `Expect` header set with
+    /// [`with_header`](struct.Request.html#method.with_header). The
+    /// timings of the first attempt are kept in
+    /// [`Timings::expectation_failed`](struct.Timings.html#structfield.expectation_failed).
+    ///
+    /// Requests without a body are sent as usual.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// use std::time::Duration;
+    ///
+    /// let file = std::fs::File::open("artifact.tar.gz")?;
+    /// let response = tinyget::post("http://example.com/upload")
+    ///     .with_body_file(file)
+    ///     .with_expect_continue(Duration::from_millis(500))
+    ///     .send()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_expect_continue(mut self, timeout: Duration) -> Request {
+        self.expect_continue = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum size of the response body in bytes, after
+    /// removing the transfer encoding. Unlimited by default.
+    ///
+    /// If the body is longer,
+    /// [`BodyTooLarge`](enum.Error.html#variant.BodyTooLarge) is
+    /// returned when reading past the limit. This also applies to
+    /// [`send_lazy`](struct.Request.html#method.send_lazy), where the
+    /// error is returned by the iterator.
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Request {
+        self.parse_options.max_body_size = Some(max_body_size);
+        self
+    }
+
+    /// Controls whether compressed responses are decompressed. On by
+    /// default.
+    ///
+    /// When on, the request is sent with `Accept-Encoding: gzip,
+    /// deflate`, and response bodies with a `Content-Encoding` of
+    /// `gzip` or `deflate`, or several of them, are decompressed as
+    /// they are read. If the request already has an `Accept-Encoding`
+    /// header, it is sent as it is, and bodies are not decompressed,
+    /// as the caller asked for the codings to handle them
+    /// themselves. The `Content-Encoding` and `Content-Length` headers are
+    /// then removed from the response, as they describe the
+    /// compressed body, whose length is available from
+    /// [`Response::wire_len`](struct.Response.html#method.wire_len).
+    /// Bodies with other codings, and `206 Partial Content` bodies,
+    /// are left as they are.
+    ///
+    /// When off, no `Accept-Encoding` is sent, and bodies are never
+    /// decompressed.
+    #[cfg(feature = "compression")]
+    pub fn with_decompression(mut self, decompress: bool) -> Request {
+        self.parse_options.decompress = decompress;
+        self
+    }
+
+    /// Sets how many times larger than its compressed size a response
+    /// body may decompress to, to protect against decompression bombs.
+    /// Bodies may always decompress to a megabyte. 100 by default.
+    ///
+    /// If the body decompresses to more,
+    /// [`DecompressionRatioTooHigh`](enum.Error.html#variant.DecompressionRatioTooHigh)
+    /// is returned while reading it.
+    #[cfg(feature = "compression")]
+    pub fn with_max_decompression_ratio(mut self, max_decompression_ratio: u32) -> Request {
+        self.parse_options.max_decompression_ratio = Some(max_decompression_ratio);
+        self
+    }
+
     /// Controls whether invalid TLS certificates are accepted.
     ///
     /// This disables certificate chain validation and should only be
@@ -141,36 +809,39 @@ impl Request {
     /// [`InvalidUtf8InBody`](enum.Error.html#variant.InvalidUtf8InBody).
     #[cfg(feature = "https")]
     pub fn send(self) -> Result<Response, Error> {
-        if self.https {
+        let request = self.prepare()?;
+        if request.https {
             #[cfg(feature = "timeout")]
             {
-                let response = match self.timeout {
-                    Some(timeout) => Connection::new(self)
-                        .send_https_timeout(std::time::Duration::from_secs(timeout))?,
-                    None => Connection::new(self).send_https()?,
+                let response = match request.timeout {
+                    Some(timeout) => {
+                        Connection::new(request).send_https_timeout(Duration::from_secs(timeout))?
+                    }
+                    None => Connection::new(request).send_https()?,
                 };
                 Response::create(response)
             }
 
             #[cfg(not(feature = "timeout"))]
             {
-                let response = Connection::new(self).send_https()?;
+                let response = Connection::new(request).send_https()?;
                 Response::create(response)
             }
         } else {
             #[cfg(feature = "timeout")]
             {
-                let response = match self.timeout {
-                    Some(timeout) => Connection::new(self)
-                        .send_timeout(std::time::Duration::from_secs(timeout))?,
-                    None => Connection::new(self).send()?,
+                let response = match request.timeout {
+                    Some(timeout) => {
+                        Connection::new(request).send_timeout(Duration::from_secs(timeout))?
+                    }
+                    None => Connection::new(request).send()?,
                 };
                 Response::create(response)
             }
 
             #[cfg(not(feature = "timeout"))]
             {
-                let response = Connection::new(self).send()?;
+                let response = Connection::new(request).send()?;
                 Response::create(response)
             }
         }
@@ -183,10 +854,11 @@ impl Request {
     /// See [`send`](struct.Request.html#method.send).
     #[cfg(feature = "https")]
     pub fn send_lazy(self) -> Result<ResponseLazy, Error> {
-        if self.https {
-            Connection::new(self).send_https()
+        let request = self.prepare()?;
+        if request.https {
+            Connection::new(request).send_https()
         } else {
-            Connection::new(self).send()
+            Connection::new(request).send()
         }
     }
 
@@ -201,22 +873,24 @@ impl Request {
     /// [`InvalidUtf8InBody`](enum.Error.html#variant.InvalidUtf8InBody).
     #[cfg(not(feature = "https"))]
     pub fn send(self) -> Result<Response, Error> {
-        if self.https {
+        let request = self.prepare()?;
+        if request.https {
             Err(Error::HttpsFeatureNotEnabled)
         } else {
             #[cfg(feature = "timeout")]
             {
-                let response = match self.timeout {
-                    Some(timeout) => Connection::new(self)
-                        .send_timeout(std::time::Duration::from_secs(timeout))?,
-                    None => Connection::new(self).send()?,
+                let response = match request.timeout {
+                    Some(timeout) => {
+                        Connection::new(request).send_timeout(Duration::from_secs(timeout))?
+                    }
+                    None => Connection::new(request).send()?,
                 };
                 Response::create(response)
             }
 
             #[cfg(not(feature = "timeout"))]
             {
-                let response = Connection::new(self).send()?;
+                let response = Connection::new(request).send()?;
                 Response::create(response)
             }
         }
@@ -229,58 +903,220 @@ impl Request {
     /// See [`send`](struct.Request.html#method.send).
     #[cfg(not(feature = "https"))]
     pub fn send_lazy(self) -> Result<ResponseLazy, Error> {
-        if self.https {
+        let request = self.prepare()?;
+        if request.https {
             Err(Error::HttpsFeatureNotEnabled)
         } else {
-            Connection::new(self).send()
+            Connection::new(request).send()
+        }
+    }
+
+    /// Prepares the request for sending.
+    fn prepare(self) -> Result<Request, Error> {
+        #[cfg(feature = "compression")]
+        {
+            self.compress_body()
+        }
+        #[cfg(not(feature = "compression"))]
+        {
+            Ok(self)
+        }
+    }
+
+    /// Prepares the body for
+    /// [`with_body_compression`](struct.Request.html#method.with_body_compression):
+    /// adds the coding to the `Content-Encoding`, and compresses
+    /// bodies in memory, so that their compressed length can be sent.
+    /// Streamed bodies are compressed as they are written, and sent
+    /// chunked. Either way, a `Content-Length` set by the caller would
+    /// be the uncompressed length, so it is removed. Requests that are sent
+    /// again, eg. when redirected, are left as they are.
+    #[cfg(feature = "compression")]
+    fn compress_body(mut self) -> Result<Request, Error> {
+        let Some(coding) = self.body_compression else {
+            return Ok(self);
+        };
+        if let Body::Bytes(bytes) = &self.body {
+            self.body_compression = None;
+            if bytes.is_empty() {
+                return Ok(self);
+            }
+            self.body = Body::Bytes(crate::compression::compress(
+                coding,
+                Vec::new(),
+                &self.body,
+            )?);
+        }
+        self.headers.remove("content-length");
+        let codings: Vec<&str> = self
+            .headers
+            .get_all("content-encoding")
+            .chain(Some(coding.name()))
+            .collect();
+        let codings = codings.join(", ");
+        self.headers.insert("Content-Encoding", codings);
+        Ok(self)
+    }
+
+    /// Returns the length of the body as it is sent, if it is known
+    /// before sending it.
+    fn body_len(&self) -> Option<u64> {
+        #[cfg(feature = "compression")]
+        if self.body_compression.is_some() {
+            return None;
         }
+        self.body.len()
     }
 
-    /// Returns the HTTP request as bytes, ready to be sent to
-    /// the server.
-    pub(crate) fn as_bytes(&self) -> Vec<u8> {
+    /// Returns the HTTP request as bytes, ready to be sent to the
+    /// server. Bodies streamed from a reader are not included, see
+    /// [`write_streamed_body`](#method.write_streamed_body).
+    ///
+    /// The method, the request target and the headers are validated
+    /// first, so that none of them can inject extra lines into the
+    /// request.
+    pub(crate) fn as_bytes(&self) -> Result<Vec<u8>, Error> {
+        if !is_token(&self.method) {
+            return Err(Error::InvalidMethod(self.method.clone()));
+        }
+        let target = self.target();
+        if !is_valid_request_target(&self.host) || !is_valid_request_target(&target) {
+            return Err(Error::InvalidRequestTarget);
+        }
+        for (k, v) in self.headers.iter().chain(&self.trailers) {
+            if !is_token(k) || !is_valid_header_value(v) {
+                return Err(Error::InvalidHeader(k.to_string()));
+            }
+        }
+        if let Body::Multipart(multipart) = &self.body {
+            multipart.validate()?;
+        }
+
         let mut http = String::with_capacity(32);
         // Add the request line and the "Host" header
-        let resource = if self.query.is_empty() {
-            self.resource.clone()
-        } else {
-            let mut query_string = String::new();
-            for (i, (k, v)) in self.query.iter().enumerate() {
-                // Check if original URL already contains query parameters
-                let separator = if i == 0 && !self.resource.contains('?') {
-                    '?'
-                } else {
-                    '&'
-                };
-                query_string.push(separator);
-                query_string.push_str(&urlencoding::encode(k));
-                query_string.push('=');
-                query_string.push_str(&urlencoding::encode(v));
-            }
-            format!("{}{}", self.resource, query_string)
-        };
         http += &format!(
             "{} {} HTTP/1.1\r\nHost: {}\r\n",
-            self.method, resource, self.host
+            self.method, target, self.host
         );
-        // Add other headers
+        // Add other headers, in the order they were added. Bodies of
+        // unknown length are always chunked, and a Content-Length
+        // alongside the chunked coding is forbidden (RFC 9112,
+        // section 6.1), as it lets requests be smuggled past proxies.
+        let body_len = self.body_len();
         for (k, v) in &self.headers {
+            if body_len.is_none() && k.eq_ignore_ascii_case("content-length") {
+                continue;
+            }
             http += &format!("{}: {}\r\n", k, v);
         }
 
-        if !self.body.is_empty()
-            && !self
-                .headers
-                .keys()
-                .any(|key| key.eq_ignore_ascii_case("content-length"))
-        {
-            http += &format!("Content-Length: {}\r\n", self.body.len());
+        #[cfg(feature = "compression")]
+        if self.adds_accept_encoding() {
+            http += &format!(
+                "Accept-Encoding: {}\r\n",
+                crate::compression::ACCEPT_ENCODING
+            );
+        }
+        if self.expect_continue_timeout().is_some() && !self.headers.contains_key("expect") {
+            http += "Expect: 100-continue\r\n";
+        }
+        match body_len {
+            Some(length) => {
+                if length > 0 && !self.headers.contains_key("content-length") {
+                    http += &format!("Content-Length: {}\r\n", length);
+                }
+            }
+            None => {
+                if !self.headers.contains_key("transfer-encoding") {
+                    http += "Transfer-Encoding: chunked\r\n";
+                }
+                if !self.trailers.is_empty() && !self.headers.contains_key("trailer") {
+                    let names: Vec<&str> = self.trailers.iter().map(|(k, _)| k).collect();
+                    http += &format!("Trailer: {}\r\n", names.join(", "));
+                }
+            }
         }
 
         http += "\r\n";
         let mut http = http.into_bytes();
-        http.extend_from_slice(&self.body);
-        http
+        if let Body::Bytes(body) = &self.body {
+            if self.expect_continue_timeout().is_none() {
+                http.extend_from_slice(body);
+            }
+        }
+        Ok(http)
+    }
+
+    /// Writes the body into `stream`, if it wasn't included in
+    /// [`as_bytes`](#method.as_bytes), after those bytes have been
+    /// written.
+    pub(crate) fn write_streamed_body<W: BodyWriter>(&self, stream: &mut W) -> Result<(), Error> {
+        #[cfg(feature = "compression")]
+        if let Some(coding) = self.body_compression {
+            return self.body.write_compressed(stream, &self.trailers, coding);
+        }
+        match &self.body {
+            Body::Bytes(body) if self.expect_continue_timeout().is_some() => {
+                stream.write_all(body)?;
+                Ok(())
+            }
+            body => body.write_streamed(stream, &self.trailers),
+        }
+    }
+
+    /// Returns the options for parsing the response.
+    pub(crate) fn parse_options(&self) -> ParseOptions {
+        #[cfg(feature = "compression")]
+        {
+            // Only decode the codings that were asked for by tinyget.
+            let mut options = self.parse_options;
+            options.decompress = self.adds_accept_encoding();
+            options
+        }
+        #[cfg(not(feature = "compression"))]
+        {
+            self.parse_options
+        }
+    }
+
+    /// Returns `true` if tinyget adds the `Accept-Encoding` header to
+    /// the request, as the caller didn't set one.
+    #[cfg(feature = "compression")]
+    fn adds_accept_encoding(&self) -> bool {
+        self.parse_options.decompress && !self.headers.contains_key("accept-encoding")
+    }
+
+    /// Returns how long to wait for `100 Continue` before sending the
+    /// body, if the request should be sent with
+    /// `Expect: 100-continue`.
+    pub(crate) fn expect_continue_timeout(&self) -> Option<Duration> {
+        match self.expect_continue {
+            Some(timeout) if self.body_len() != Some(0) => Some(timeout),
+            _ => None,
+        }
+    }
+
+    /// Returns the resource with the query parameters, ie. the
+    /// request target. The fragment is left out, as it is never sent
+    /// to the server (RFC 9110, section 7.1).
+    fn target(&self) -> URL {
+        let path = match self.resource.find('#') {
+            Some(index) => &self.resource[..index],
+            None => &self.resource,
+        };
+        let mut target = URL::from(path);
+        for (i, param) in self.query.iter().enumerate() {
+            target.push(if i == 0 { '?' } else { '&' });
+            match &param.raw {
+                Some(raw) => target.push_str(raw),
+                None => {
+                    target += &encode_query_component(&param.key, self.query_encoding);
+                    target.push('=');
+                    target += &encode_query_component(&param.value, self.query_encoding);
+                }
+            }
+        }
+        target
     }
 
     /// Returns the redirected version of this Request, unless an
@@ -303,28 +1139,33 @@ impl Request {
             }
         };
 
+        let target = self.target();
+        // The new url replaces the query parameters along with the
+        // rest of the resource.
         if url.contains("://") {
             let (https, host, resource) = parse_url(url);
-            let new_resource = inherit_fragment(resource, &self.resource);
+            let (new_resource, query) = split_query(inherit_fragment(resource, &self.resource));
 
-            self.redirects.push((self.https, self.host, self.resource));
+            self.redirects.push((self.https, self.host, target));
 
             self.https = https;
             self.resource = new_resource;
+            self.query = query;
             self.host = host;
         } else {
             // The url does not have the protocol part, assuming it's
             // a relative resource.
-            let new_resource = inherit_fragment(url, &self.resource);
+            let (new_resource, query) = split_query(inherit_fragment(url, &self.resource));
 
-            self.redirects
-                .push((self.https, self.host.clone(), self.resource));
+            self.redirects.push((self.https, self.host.clone(), target));
 
             self.resource = new_resource;
+            self.query = query;
         }
 
-        let is_this_url = |(https_, host_, resource_): &(bool, URL, URL)| {
-            resource_ == &self.resource && host_ == &self.host && https_ == &self.https
+        let target = self.target();
+        let is_this_url = |(https_, host_, target_): &(bool, URL, URL)| {
+            target_ == &target && host_ == &self.host && https_ == &self.https
         };
 
         if self.redirects.len() > self.max_redirects {
@@ -337,6 +1178,63 @@ impl Request {
     }
 }
 
+/// Returns `true` if `s` is a valid RFC 9110 token, ie. a valid
+/// method or header name.
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Returns `true` if `s` can be sent as a header value without
+/// ending the header line.
+fn is_valid_header_value(s: &str) -> bool {
+    !s.bytes().any(|b| b == b'\r' || b == b'\n' || b == b'\0')
+}
+
+/// Returns `true` if `s` contains no control characters, which could
+/// end the request line.
+fn is_valid_request_target(s: &str) -> bool {
+    !s.chars().any(|c| c.is_ascii_control())
+}
+
+fn encode_query_component(s: &str, encoding: QueryEncoding) -> String {
+    let encoded = urlencoding::encode(s);
+    match encoding {
+        QueryEncoding::Percent => encoded.into_owned(),
+        QueryEncoding::Form => encoded.replace("%20", "+"),
+    }
+}
+
+/// Splits the query parameters off of the resource, keeping the
+/// fragment, if any, in the resource.
+fn split_query(resource: URL) -> (URL, Vec<QueryParam>) {
+    let query_end = resource.find('#').unwrap_or(resource.len());
+    let query_start = match resource[..query_end].find('?') {
+        Some(index) => index,
+        None => return (resource, Vec::new()),
+    };
+    let query = resource[query_start + 1..query_end]
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let decode = |s: &str| {
+                urlencoding::decode(s)
+                    .map(|s| s.into_owned())
+                    .unwrap_or_else(|_| s.to_string())
+            };
+            QueryParam {
+                key: decode(key),
+                value: decode(value),
+                raw: Some(pair.to_string()),
+            }
+        })
+        .collect();
+    let resource = format!("{}{}", &resource[..query_start], &resource[query_end..]);
+    (resource, query)
+}
+
 fn parse_url(url: URL) -> (bool, URL, URL) {
     let mut first = URL::new();
     let mut second = URL::new();
@@ -373,11 +1271,290 @@ pub fn post<T: Into<URL>>(url: T) -> Request {
     Request::new(url).with_method("POST")
 }
 
-#[cfg(all(test, feature = "https"))]
+#[cfg(test)]
 mod tests {
-    use super::Request;
+    use super::{QueryEncoding, Request};
+    use crate::Error;
+
+    /// Creates a request whose head only has the headers given to it.
+    fn plain_request(url: &str) -> Request {
+        let request = Request::new(url);
+        #[cfg(feature = "compression")]
+        let request = request.with_decompression(false);
+        request
+    }
+
+    #[test]
+    fn emits_headers_in_order() {
+        let request = plain_request("http://example.com/path")
+            .with_header("X-Signature", "abc")
+            .append_header("Accept", "text/html")
+            .append_header("accept", "text/plain")
+            .with_header("X-SIGNATURE", "def")
+            .append_header("X-Removed", "1")
+            .remove_header("x-removed");
+
+        assert_eq!(
+            String::from_utf8(request.as_bytes().unwrap()).unwrap(),
+            "GET /path HTTP/1.1\r\nHost: example.com:80\r\nX-SIGNATURE: def\r\n\
+             Accept: text/html\r\naccept: text/plain\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn emits_typed_headers() {
+        use crate::{CacheControl, ContentType, ETag};
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let request = plain_request("http://example.com/feed")
+            .with_content_type(ContentType::new("text/plain").with_param("charset", "utf-8"))
+            .with_cache_control(CacheControl::new().with_directive("no-cache"))
+            .with_if_none_match([ETag::strong("a"), ETag::weak("b")])
+            .with_if_modified_since(UNIX_EPOCH + Duration::from_secs(784_111_777))
+            .with_range(500, None);
+
+        assert_eq!(
+            String::from_utf8(request.as_bytes().unwrap()).unwrap(),
+            "GET /feed HTTP/1.1\r\nHost: example.com:80\r\n\
+             Content-Type: text/plain; charset=utf-8\r\nCache-Control: no-cache\r\n\
+             If-None-Match: \"a\", W/\"b\"\r\n\
+             If-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT\r\nRange: bytes=500-\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn chunked_bodies_have_no_content_length() {
+        let request = plain_request("http://example.com/upload")
+            .with_header("Content-Length", "5")
+            .with_body_reader(&b"hello"[..], None);
+        assert_eq!(
+            String::from_utf8(request.as_bytes().unwrap()).unwrap(),
+            "GET /upload HTTP/1.1\r\nHost: example.com:80\r\n\
+             Transfer-Encoding: chunked\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn encodes_form_bodies() {
+        let request = plain_request("http://example.com/login").with_form([
+            ("user", "tiny get"),
+            ("scope", "a&b=c"),
+            ("user", "é"),
+        ]);
+        assert_eq!(
+            String::from_utf8(request.as_bytes().unwrap()).unwrap(),
+            "GET /login HTTP/1.1\r\nHost: example.com:80\r\n\
+             Content-Type: application/x-www-form-urlencoded\r\nContent-Length: 41\r\n\r\n\
+             user=tiny+get&scope=a%26b%3Dc&user=%C3%A9"
+        );
+
+        let request = Request::new("http://example.com/")
+            .with_header(
+                "content-type",
+                "application/x-www-form-urlencoded; charset=utf-8",
+            )
+            .with_form([("a", "b")]);
+        let fields: Vec<(&str, &str)> = request.headers.iter().collect();
+        assert_eq!(
+            fields,
+            [(
+                "content-type",
+                "application/x-www-form-urlencoded; charset=utf-8"
+            )]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serializes_structs_into_queries_and_forms() {
+        let params = std::collections::BTreeMap::from([
+            ("tag", vec![Some("a b"), None, Some("c")]),
+            ("page", vec![Some("2")]),
+        ]);
+        let request = Request::new("http://example.com/search?q=x")
+            .with_query_struct(&params)
+            .unwrap();
+        assert!(request
+            .as_bytes()
+            .unwrap()
+            .starts_with(b"GET /search?q=x&page=2&tag=a%20b&tag=c HTTP/1.1\r\n"));
+
+        let request = Request::new("http://example.com/")
+            .with_form_struct(&params)
+            .unwrap();
+        assert_eq!(
+            request.body,
+            crate::body::Body::Bytes(b"page=2&tag=a+b&tag=c".to_vec())
+        );
+
+        let result = Request::new("http://example.com/").with_query_struct(&[1, 2]);
+        assert!(matches!(result, Err(Error::SerializePairs(_))));
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn accepts_compressed_responses() {
+        let head = Request::new("http://example.com/").as_bytes().unwrap();
+        assert!(String::from_utf8(head)
+            .unwrap()
+            .contains("\r\nAccept-Encoding: gzip, deflate\r\n"));
+
+        let request = Request::new("http://example.com/").with_header("Accept-Encoding", "gzip");
+        let head = String::from_utf8(request.as_bytes().unwrap()).unwrap();
+        assert_eq!(head.matches("Accept-Encoding").count(), 1);
+    }
 
     #[test]
+    #[cfg(feature = "compression")]
+    fn compresses_bodies() {
+        use crate::Coding;
+
+        let request = plain_request("http://example.com/")
+            .with_body("hello ".repeat(100))
+            .with_body_compression(Coding::Gzip)
+            .compress_body()
+            .unwrap();
+        let length = request.body.len().unwrap();
+        assert!(length < 600);
+        let head = String::from_utf8_lossy(&request.as_bytes().unwrap()).into_owned();
+        assert!(head.contains("\r\nContent-Encoding: gzip\r\n"));
+        assert!(head.contains(&format!("\r\nContent-Length: {}\r\n", length)));
+
+        let request = plain_request("http://example.com/")
+            .with_body_reader(&b"hello"[..], Some(5))
+            .with_body_compression(Coding::Deflate)
+            .compress_body()
+            .unwrap();
+        let head = String::from_utf8(request.as_bytes().unwrap()).unwrap();
+        assert!(head.contains("\r\nContent-Encoding: deflate\r\n"));
+        assert!(head.contains("\r\nTransfer-Encoding: chunked\r\n"));
+        assert!(!head.contains("Content-Length"));
+
+        let request = plain_request("http://example.com/")
+            .with_body_compression(Coding::Gzip)
+            .compress_body()
+            .unwrap();
+        let head = String::from_utf8(request.as_bytes().unwrap()).unwrap();
+        assert!(!head.contains("Content-Encoding"));
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn adds_to_the_content_encoding_of_compressed_bodies() {
+        use crate::Coding;
+
+        let request = plain_request("http://example.com/")
+            .with_header("Content-Encoding", "br")
+            .with_body("hello")
+            .with_body_compression(Coding::Gzip)
+            .compress_body()
+            .unwrap();
+        let head = String::from_utf8_lossy(&request.as_bytes().unwrap()).into_owned();
+        assert!(head.contains("\r\nContent-Encoding: br, gzip\r\n"));
+        assert_eq!(head.matches("Content-Encoding").count(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn replaces_the_content_length_of_compressed_bodies() {
+        use crate::Coding;
+
+        let request = plain_request("http://example.com/")
+            .with_header("Content-Length", "600")
+            .with_body("hello ".repeat(100))
+            .with_body_compression(Coding::Gzip)
+            .compress_body()
+            .unwrap();
+        let length = request.body.len().unwrap();
+        let head = String::from_utf8_lossy(&request.as_bytes().unwrap()).into_owned();
+        assert_eq!(head.matches("Content-Length").count(), 1);
+        assert!(head.contains(&format!("\r\nContent-Length: {}\r\n", length)));
+
+        let request = plain_request("http://example.com/")
+            .with_header("Content-Length", "5")
+            .with_body_reader(&b"hello"[..], Some(5))
+            .with_body_compression(Coding::Gzip)
+            .compress_body()
+            .unwrap();
+        let head = String::from_utf8(request.as_bytes().unwrap()).unwrap();
+        assert!(head.contains("\r\nTransfer-Encoding: chunked\r\n"));
+        assert!(!head.contains("Content-Length"));
+    }
+
+    #[test]
+    fn keeps_query_order_and_duplicates() {
+        let request = Request::new("http://example.com/search?q=a+b&page=1#results")
+            .append_query("tag", "x y")
+            .with_queries(vec![("tag", "z"), ("lang", "en")])
+            .with_query("page", "2")
+            .remove_query("lang");
+        assert_eq!(request.target(), "/search?q=a+b&page=2&tag=x%20y&tag=z");
+
+        let request = request.with_query_encoding(QueryEncoding::Form);
+        assert_eq!(request.target(), "/search?q=a+b&page=2&tag=x+y&tag=z");
+
+        let request = Request::new("http://example.com/about#contact");
+        assert_eq!(request.target(), "/about");
+    }
+
+    #[test]
+    fn redirect_replaces_query() {
+        let request = Request::new("http://example.com/a?x=1")
+            .with_query("y", "2")
+            .redirect_to("/b?z=3".to_string())
+            .unwrap();
+        assert_eq!(request.target(), "/b?z=3");
+    }
+
+    #[test]
+    fn redirect_keeps_fragment() {
+        let request = Request::new("http://example.com/a#foo");
+        let request = request.redirect_to("/b".to_string()).unwrap();
+        assert_eq!(request.resource, "/b#foo");
+        let request = request.redirect_to("/c#bar".to_string()).unwrap();
+        assert_eq!(request.resource, "/c#bar");
+        assert_eq!(request.target(), "/c");
+    }
+
+    #[test]
+    fn rejects_header_injection() {
+        let request = Request::new("http://example.com").with_header("X-A", "a\r\nX-B: b");
+        assert!(matches!(request.as_bytes(), Err(Error::InvalidHeader(name)) if name == "X-A"));
+
+        let request = Request::new("http://example.com").with_header("X-A\r\nX-B", "b");
+        assert!(matches!(request.as_bytes(), Err(Error::InvalidHeader(_))));
+
+        let request = Request::new("http://example.com").with_header("", "b");
+        assert!(matches!(request.as_bytes(), Err(Error::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn rejects_invalid_methods_and_targets() {
+        let request = Request::new("http://example.com").with_method("GET / HTTP/1.1\r\n");
+        assert!(matches!(request.as_bytes(), Err(Error::InvalidMethod(_))));
+
+        let request = Request::new("http://example.com/a\r\nX-B: b");
+        assert!(matches!(
+            request.as_bytes(),
+            Err(Error::InvalidRequestTarget)
+        ));
+
+        // Query pairs from the URL are sent as they were given.
+        let request = Request::new("http://example.com/a?x=1\r\nX-Injected: y");
+        assert!(matches!(
+            request.as_bytes(),
+            Err(Error::InvalidRequestTarget)
+        ));
+
+        let request = Request::new("http://example.com\0/a");
+        assert!(matches!(
+            request.as_bytes(),
+            Err(Error::InvalidRequestTarget)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "https")]
     fn stores_dangerous_tls_options() {
         let request = Request::new("https://example.com")
             .danger_accept_invalid_certs(true)
@@ -1,9 +1,94 @@
-use crate::{connection::HttpStream, Error};
-use std::collections::HashMap;
-use std::io::{BufReader, Bytes, Read};
-use std::str;
+use crate::{connection::HttpStream, Error, Headers, StatusCode, Timings};
+use crate::{CacheControl, ContentRange, ContentType, ETag, RetryAfter};
+use std::borrow::Cow;
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::SocketAddr;
+use std::time::{Instant, SystemTime};
+use std::{fmt, str};
 
 const BACKING_READ_BUFFER_LENGTH: usize = 16 * 1024;
+/// How much of the body is kept in an
+/// [`HttpStatus`](enum.Error.html#variant.HttpStatus) error.
+const ERROR_BODY_SNIPPET_LENGTH: usize = 1024;
+/// How much of the body's length, as announced by the server, is
+/// allocated up front. Longer bodies grow the buffer as they arrive.
+const MAX_BODY_PREALLOCATION: usize = 1024 * 1024;
+#[cfg(feature = "compression")]
+const DEFAULT_MAX_DECOMPRESSION_RATIO: u32 = 100;
+
+/// How strictly the head of a response (the status line, header
+/// fields, and trailer fields) is parsed. Set with
+/// [`Request::with_parse_mode`](struct.Request.html#method.with_parse_mode).
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum ParseMode {
+    /// Only accept responses that follow RFC 9112. Each deviation
+    /// fails the response with a specific error:
+    /// [`BareLineFeed`](enum.Error.html#variant.BareLineFeed),
+    /// [`ObsoleteLineFolding`](enum.Error.html#variant.ObsoleteLineFolding),
+    /// [`MalformedHeader`](enum.Error.html#variant.MalformedHeader)
+    /// (also for whitespace between a field name and its `:`), or
+    /// [`InvalidUtf8InResponse`](enum.Error.html#variant.InvalidUtf8InResponse).
+    Strict,
+    /// Accept the deviations that are common in real-world servers:
+    /// lines may end in a bare `\n`, folded header values are joined
+    /// into one line with a single space, values that are not UTF-8
+    /// are decoded as ISO-8859-1, whitespace after a field name is
+    /// removed, and lines without a `:` are ignored. This is the
+    /// default.
+    #[default]
+    Lenient,
+}
+
+/// The HTTP version of a response, from its status line.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum HttpVersion {
+    /// HTTP/1.0
+    Http10,
+    /// HTTP/1.1, or a later HTTP/1.x version, which are compatible
+    /// with it.
+    Http11,
+}
+
+impl fmt::Display for HttpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HttpVersion::Http10 => write!(f, "HTTP/1.0"),
+            HttpVersion::Http11 => write!(f, "HTTP/1.1"),
+        }
+    }
+}
+
+/// Options for parsing the response, set with the `Request::with_*`
+/// functions. `None` means unlimited.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(not(feature = "compression"), derive(Default))]
+pub(crate) struct ParseOptions {
+    pub(crate) mode: ParseMode,
+    pub(crate) max_line_length: Option<usize>,
+    pub(crate) max_header_count: Option<usize>,
+    pub(crate) max_headers_size: Option<usize>,
+    pub(crate) max_body_size: Option<usize>,
+    #[cfg(feature = "compression")]
+    pub(crate) decompress: bool,
+    #[cfg(feature = "compression")]
+    pub(crate) max_decompression_ratio: Option<u32>,
+}
+
+/// Decompression is on by default.
+#[cfg(feature = "compression")]
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions {
+            mode: ParseMode::default(),
+            max_line_length: None,
+            max_header_count: None,
+            max_headers_size: None,
+            max_body_size: None,
+            decompress: true,
+            max_decompression_ratio: Some(DEFAULT_MAX_DECOMPRESSION_RATIO),
+        }
+    }
+}
 
 /// An HTTP response.
 ///
@@ -20,31 +105,63 @@ const BACKING_READ_BUFFER_LENGTH: usize = 16 * 1024;
 #[derive(Clone, PartialEq, Debug)]
 pub struct Response {
     /// The status code of the response, eg. 404.
-    pub status_code: i32,
+    pub status_code: StatusCode,
     /// The reason phrase of the response, eg. "Not Found".
     pub reason_phrase: String,
-    /// The headers of the response. The header field names (the
-    /// keys) are all lowercase.
-    pub headers: HashMap<String, String>,
+    /// The headers of the response, in the order they were received.
+    pub headers: Headers,
+    /// The trailer fields sent after a chunked body. Empty if the
+    /// response was not chunked or the server did not send any
+    /// trailers.
+    pub trailers: Headers,
 
     body: Vec<u8>,
+    wire_len: usize,
+    version: HttpVersion,
+    remote_addr: Option<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+    timings: Timings,
 }
 
 impl Response {
     pub(crate) fn create(mut parent: ResponseLazy) -> Result<Response, Error> {
         let mut body = Vec::new();
-        if parent.status_code != 204 && parent.status_code != 304 {
-            for byte in &mut parent {
-                let (byte, length) = byte?;
-                body.reserve(length);
-                body.push(byte);
+        if parent.status_code != StatusCode::NO_CONTENT
+            && parent.status_code != StatusCode::NOT_MODIFIED
+        {
+            let max_body_size = parent.options.max_body_size.unwrap_or(usize::MAX);
+            loop {
+                let length = parent.fill_body()?;
+                if length == 0 {
+                    break;
+                }
+                // Don't trust the server's length beyond our own limits.
+                body.reserve(
+                    parent
+                        .expected_len()
+                        .min(max_body_size - body.len())
+                        .min(MAX_BODY_PREALLOCATION),
+                );
+                body.extend_from_slice(parent.body_buffer(length));
+                parent.consume(length);
             }
         }
+        let head_read_at = parent.head_read_at;
+        parent
+            .timings
+            .download
+            .get_or_insert_with(|| head_read_at.elapsed());
 
         let ResponseLazy {
             status_code,
             reason_phrase,
             headers,
+            trailers,
+            version,
+            remote_addr,
+            local_addr,
+            timings,
+            wire_len,
             ..
         } = parent;
 
@@ -52,10 +169,111 @@ impl Response {
             status_code,
             reason_phrase,
             headers,
+            trailers,
             body,
+            wire_len,
+            version,
+            remote_addr,
+            local_addr,
+            timings,
         })
     }
 
+    /// Returns how long each phase of the request took.
+    pub fn timings(&self) -> &Timings {
+        &self.timings
+    }
+
+    /// Returns the HTTP version the server responded with.
+    pub fn version(&self) -> HttpVersion {
+        self.version
+    }
+
+    /// Returns the address of the server the response was received
+    /// from, after resolving the host and following redirections.
+    /// `None` if the socket could not report it.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Returns the local address of the connection the response was
+    /// received on. `None` if the socket could not report it.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Returns the length of the body as it was received, before
+    /// removing its `Content-Encoding`. This is the length of the
+    /// body unless it was decompressed, see the `compression`
+    /// feature.
+    pub fn wire_len(&self) -> usize {
+        self.wire_len
+    }
+
+    /// Returns the parsed `Content-Type` header, see
+    /// [`Headers::content_type`](struct.Headers.html#method.content_type).
+    pub fn content_type(&self) -> Option<ContentType> {
+        self.headers.content_type()
+    }
+
+    /// Returns the parsed `Content-Length` header, as it was received, see
+    /// [`Headers::content_length`](struct.Headers.html#method.content_length).
+    /// With the `compression` feature, this is `None` for bodies that
+    /// were decompressed, as the header is removed along with the
+    /// `Content-Encoding`. Their received length is
+    /// [`wire_len`](struct.Response.html#method.wire_len).
+    pub fn content_length(&self) -> Option<u64> {
+        self.headers.content_length()
+    }
+
+    /// Returns the parsed `Cache-Control` directives, see
+    /// [`Headers::cache_control`](struct.Headers.html#method.cache_control).
+    pub fn cache_control(&self) -> Option<CacheControl> {
+        self.headers.cache_control()
+    }
+
+    /// Returns the parsed `ETag` header, see
+    /// [`Headers::etag`](struct.Headers.html#method.etag).
+    pub fn etag(&self) -> Option<ETag> {
+        self.headers.etag()
+    }
+
+    /// Returns the parsed `Date` header, see
+    /// [`Headers::date`](struct.Headers.html#method.date).
+    pub fn date(&self) -> Option<SystemTime> {
+        self.headers.date()
+    }
+
+    /// Returns the parsed `Last-Modified` header, see
+    /// [`Headers::last_modified`](struct.Headers.html#method.last_modified).
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        self.headers.last_modified()
+    }
+
+    /// Returns the parsed `Expires` header, see
+    /// [`Headers::expires`](struct.Headers.html#method.expires).
+    pub fn expires(&self) -> Option<SystemTime> {
+        self.headers.expires()
+    }
+
+    /// Returns the parsed `Retry-After` header, see
+    /// [`Headers::retry_after`](struct.Headers.html#method.retry_after).
+    pub fn retry_after(&self) -> Option<RetryAfter> {
+        self.headers.retry_after()
+    }
+
+    /// Returns the parsed `Content-Range` header, see
+    /// [`Headers::content_range`](struct.Headers.html#method.content_range).
+    pub fn content_range(&self) -> Option<ContentRange> {
+        self.headers.content_range()
+    }
+
+    /// Returns whether the server accepts byte ranges, see
+    /// [`Headers::accepts_byte_ranges`](struct.Headers.html#method.accepts_byte_ranges).
+    pub fn accepts_byte_ranges(&self) -> bool {
+        self.headers.accepts_byte_ranges()
+    }
+
     /// Returns the body as an `&str`.
     ///
     /// # Errors
@@ -82,6 +300,158 @@ impl Response {
         }
     }
 
+    /// Decodes the body into text, with the charset given by a byte
+    /// order mark at its start, the `charset` parameter of the
+    /// `Content-Type` header, or for HTML, a `<meta charset>`
+    /// declaration near its start, in that order of precedence.
+    /// Bodies without a charset are read as UTF-8, like with
+    /// [`as_str`](#method.as_str), and borrowed rather than copied.
+    ///
+    /// Only UTF-8 and UTF-16 are supported, unless the `charset`
+    /// feature is enabled, which adds all the charsets of the WHATWG
+    /// Encoding Standard, eg. `windows-1252` and `Shift_JIS`.
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`UnsupportedCharset`](enum.Error.html#variant.UnsupportedCharset)
+    /// if the charset is not supported, and
+    /// [`InvalidUtf8InBody`](enum.Error.html#variant.InvalidUtf8InBody)
+    /// or
+    /// [`InvalidTextInBody`](enum.Error.html#variant.InvalidTextInBody)
+    /// if the body is not valid in its charset. Malformed bytes are
+    /// never replaced.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// let response = tinyget::get("http://example.jp/").send()?;
+    /// println!("{}", response.text()?);
+    /// # Ok(()) }
+    /// ```
+    pub fn text(&self) -> Result<Cow<'_, str>, Error> {
+        crate::charset::decode(&self.body, self.content_type().as_ref())
+    }
+
+    /// Decodes the body into text with the charset called `label`, eg.
+    /// `"windows-1252"`, ignoring the charset the response declares.
+    /// A byte order mark of that charset is skipped. See
+    /// [`text`](#method.text) for the supported charsets and errors.
+    pub fn text_with_charset(&self, label: &str) -> Result<Cow<'_, str>, Error> {
+        crate::charset::decode_with_label(&self.body, label)
+    }
+
+    /// Deserializes the body from JSON into a `T`, which can borrow
+    /// strings from the body.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`Json`](enum.Error.html#variant.Json) error if the
+    /// body is not valid JSON, or doesn't match `T`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// let response = tinyget::get("http://httpbin.org/ip").send()?;
+    /// let body: std::collections::HashMap<&str, &str> = response.json()?;
+    /// println!("{}", body["origin"]);
+    /// # Ok(()) }
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn json<'a, T: serde::Deserialize<'a>>(&'a self) -> Result<T, Error> {
+        crate::json::from_slice(&self.body)
+    }
+
+    /// Deserializes the body from CBOR into a `T`, after checking that
+    /// the `Content-Type` is `application/cbor`, or another type with
+    /// the `+cbor` suffix.
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`UnexpectedContentType`](enum.Error.html#variant.UnexpectedContentType)
+    /// if the response is not CBOR, and a
+    /// [`Cbor`](enum.Error.html#variant.Cbor) error if the body is not
+    /// valid CBOR, or doesn't match `T`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// let response = tinyget::get("http://example.com/readings")
+    ///     .with_header("Accept", "application/cbor")
+    ///     .send()?;
+    /// let readings: Vec<(u64, f64)> = response.cbor()?;
+    /// # Ok(()) }
+    /// ```
+    #[cfg(feature = "cbor")]
+    pub fn cbor<T: serde::de::DeserializeOwned>(&self) -> Result<T, Error> {
+        check_content_type(
+            &self.headers,
+            crate::cbor::CONTENT_TYPE,
+            crate::cbor::accepts,
+        )?;
+        crate::cbor::from_reader(&self.body[..])
+    }
+
+    /// Deserializes the body from MessagePack into a `T`, which can
+    /// borrow strings from the body, after checking that the
+    /// `Content-Type` is `application/msgpack`,
+    /// `application/x-msgpack` or `application/vnd.msgpack`.
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`UnexpectedContentType`](enum.Error.html#variant.UnexpectedContentType)
+    /// if the response is not MessagePack, and a
+    /// [`MsgPack`](enum.Error.html#variant.MsgPack) error if the body
+    /// is not valid MessagePack, or doesn't match `T`.
+    #[cfg(feature = "msgpack")]
+    pub fn msgpack<'a, T: serde::Deserialize<'a>>(&'a self) -> Result<T, Error> {
+        check_content_type(
+            &self.headers,
+            crate::msgpack::CONTENT_TYPE,
+            crate::msgpack::accepts,
+        )?;
+        crate::msgpack::from_slice(&self.body)
+    }
+
+    /// Parses the body as `application/x-www-form-urlencoded`, like
+    /// the responses of OAuth token endpoints, and returns the
+    /// decoded `(name, value)` pairs in order. Names that appear
+    /// multiple times are returned once for each value.
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`InvalidUtf8InBody`](enum.Error.html#variant.InvalidUtf8InBody)
+    /// if the body, or a percent-decoded name or value, is not UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), tinyget::Error> {
+    /// let response = tinyget::post("http://example.com/oauth/token")
+    ///     .with_form([("grant_type", "client_credentials")])
+    ///     .send()?;
+    /// for (name, value) in response.form()? {
+    ///     println!("{} = {}", name, value);
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn form(&self) -> Result<Vec<(String, String)>, Error> {
+        self.as_str()?
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                Ok((decode_form_component(name)?, decode_form_component(value)?))
+            })
+            .collect()
+    }
+
     /// Returns a reference to the contained bytes of the body. If you
     /// want the `Vec<u8>` itself, use
     /// [`into_bytes()`](#method.into_bytes) instead.
@@ -119,6 +489,40 @@ impl Response {
     pub fn into_bytes(self) -> Vec<u8> {
         self.body
     }
+
+    /// Turns the `Response` into an error if its status code is a
+    /// client error (4xx) or a server error (5xx).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HttpStatus`](enum.Error.html#variant.HttpStatus),
+    /// containing the status code, the headers, and the first
+    /// kilobyte of the body, which often describes the problem.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let url = "http://example.org/";
+    /// let response = tinyget::get(url).send()?.error_for_status()?;
+    /// println!("{}", response.as_str()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn error_for_status(self) -> Result<Response, Error> {
+        if self.status_code.is_client_error() || self.status_code.is_server_error() {
+            let mut body = self.body;
+            body.truncate(ERROR_BODY_SNIPPET_LENGTH);
+            Err(Error::HttpStatus {
+                status_code: self.status_code,
+                reason_phrase: self.reason_phrase,
+                headers: self.headers,
+                body,
+            })
+        } else {
+            Ok(self)
+        }
+    }
 }
 
 /// An HTTP response, which is loaded lazily.
@@ -139,6 +543,12 @@ impl Response {
 /// `chunked`: then it will reflect how many bytes are left of the
 /// current chunk.
 ///
+/// `ResponseLazy` also implements [`Read`] and [`BufRead`], which
+/// read the body in bulk instead of byte by byte. Errors other than
+/// I/O errors are returned as `io::Error`s of the kind `InvalidData`,
+/// wrapping the [`Error`](enum.Error.html), and converting them back
+/// into an `Error` with `?` unwraps them.
+///
 /// # Example
 /// ```no_run
 /// // This is how the normal Response works behind the scenes, and
@@ -155,167 +565,404 @@ impl Response {
 /// # }
 ///
 /// ```
+///
+/// Copying a large body into a file:
+/// ```no_run
+/// # fn main() -> Result<(), tinyget::Error> {
+/// let mut response = tinyget::get("http://example.com/large").send_lazy()?;
+/// let mut file = std::fs::File::create("large")?;
+/// std::io::copy(&mut response, &mut file)?;
+/// # Ok(())
+/// # }
+/// ```
 pub struct ResponseLazy {
     /// The status code of the response, eg. 404.
-    pub status_code: i32,
+    pub status_code: StatusCode,
     /// The reason phrase of the response, eg. "Not Found".
     pub reason_phrase: String,
-    /// The headers of the response. The header field names (the
-    /// keys) are all lowercase.
-    pub headers: HashMap<String, String>,
+    /// The headers of the response, in the order they were received.
+    pub headers: Headers,
+    /// The trailer fields sent after a chunked body. These are only
+    /// available after the body has been read to the end.
+    pub trailers: Headers,
 
-    stream: HttpStreamBytes,
+    stream: HttpStreamReader,
     state: HttpStreamState,
+    options: ParseOptions,
+    body_len: usize,
+    wire_len: usize,
+    #[cfg(feature = "compression")]
+    decoder: Option<crate::compression::Decoder>,
+    version: HttpVersion,
+    remote_addr: Option<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+    pub(crate) timings: Timings,
+    head_read_at: Instant,
 }
 
-type HttpStreamBytes = Bytes<BufReader<HttpStream>>;
+pub(crate) type HttpStreamReader = BufReader<HttpStream>;
+
+/// Wraps `stream` in the buffer responses are read through.
+pub(crate) fn buffer_stream(stream: HttpStream) -> HttpStreamReader {
+    BufReader::with_capacity(BACKING_READ_BUFFER_LENGTH, stream)
+}
 
 impl ResponseLazy {
-    pub(crate) fn from_stream(stream: HttpStream) -> Result<ResponseLazy, Error> {
-        let mut stream = BufReader::with_capacity(BACKING_READ_BUFFER_LENGTH, stream).bytes();
+    /// Reads the head of the response from `stream`. Interim (1xx)
+    /// responses before the final one are skipped, except for
+    /// `101 Switching Protocols`, and `100 Continue` if
+    /// `stop_at_continue` is set.
+    pub(crate) fn from_stream(
+        mut stream: HttpStreamReader,
+        options: ParseOptions,
+        mut timings: Timings,
+        request_sent: Instant,
+        stop_at_continue: bool,
+    ) -> Result<ResponseLazy, Error> {
+        let remote_addr = stream.get_ref().tcp().peer_addr().ok();
+        let local_addr = stream.get_ref().tcp().local_addr().ok();
+        stream.fill_buf()?;
+        timings.first_byte = request_sent.elapsed();
         let ResponseMetadata {
+            version,
             status_code,
             reason_phrase,
             headers,
             state,
-        } = read_metadata(&mut stream)?;
+        } = loop {
+            let metadata = read_metadata(&mut stream, &options)?;
+            let status_code = metadata.status_code;
+            if !status_code.is_informational()
+                || status_code == StatusCode::SWITCHING_PROTOCOLS
+                || (stop_at_continue && status_code == StatusCode::CONTINUE)
+            {
+                break metadata;
+            }
+        };
+        #[cfg(feature = "compression")]
+        let mut headers = headers;
+        #[cfg(feature = "compression")]
+        let decoder = match status_code {
+            // Partial content can't be decoded on its own, and the
+            // other statuses don't have a body to decode.
+            StatusCode::PARTIAL_CONTENT | StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED => None,
+            _ if status_code.is_informational() || !options.decompress => None,
+            _ => crate::compression::Decoder::from_headers(&mut headers),
+        };
 
         Ok(ResponseLazy {
             status_code,
             reason_phrase,
             headers,
+            trailers: Headers::new(),
             stream,
             state,
+            options,
+            body_len: 0,
+            wire_len: 0,
+            #[cfg(feature = "compression")]
+            decoder,
+            version,
+            remote_addr,
+            local_addr,
+            timings,
+            head_read_at: Instant::now(),
         })
     }
+
+    /// Returns the connection this response was read from, for
+    /// reading the next response from it, along with the timings so
+    /// far.
+    pub(crate) fn into_stream(self) -> (HttpStreamReader, Timings) {
+        (self.stream, self.timings)
+    }
+
+    /// Deserializes the body from JSON into a `T` as it is read,
+    /// without loading the whole body into memory first.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`Json`](enum.Error.html#variant.Json) error if the
+    /// body is not valid JSON, or doesn't match `T`, and the other
+    /// errors of reading the body as they are.
+    #[cfg(feature = "json")]
+    pub fn json<T: serde::de::DeserializeOwned>(self) -> Result<T, Error> {
+        crate::json::from_reader(self)
+    }
+
+    /// Deserializes the body from CBOR into a `T` as it is read, like
+    /// [`Response::cbor`](struct.Response.html#method.cbor).
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`UnexpectedContentType`](enum.Error.html#variant.UnexpectedContentType)
+    /// before reading anything if the response is not CBOR, a
+    /// [`Cbor`](enum.Error.html#variant.Cbor) error if the body is not
+    /// valid CBOR, or doesn't match `T`, and the other errors of
+    /// reading the body as they are.
+    #[cfg(feature = "cbor")]
+    pub fn cbor<T: serde::de::DeserializeOwned>(self) -> Result<T, Error> {
+        check_content_type(
+            &self.headers,
+            crate::cbor::CONTENT_TYPE,
+            crate::cbor::accepts,
+        )?;
+        crate::cbor::from_reader(self)
+    }
+
+    /// Deserializes the body from MessagePack into a `T` as it is
+    /// read, like [`Response::msgpack`](struct.Response.html#method.msgpack).
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`UnexpectedContentType`](enum.Error.html#variant.UnexpectedContentType)
+    /// before reading anything if the response is not MessagePack, a
+    /// [`MsgPack`](enum.Error.html#variant.MsgPack) error if the body
+    /// is not valid MessagePack, or doesn't match `T`, and the other
+    /// errors of reading the body as they are.
+    #[cfg(feature = "msgpack")]
+    pub fn msgpack<T: serde::de::DeserializeOwned>(self) -> Result<T, Error> {
+        check_content_type(
+            &self.headers,
+            crate::msgpack::CONTENT_TYPE,
+            crate::msgpack::accepts,
+        )?;
+        crate::msgpack::from_reader(self)
+    }
+
+    /// Returns how long each phase of the request took. The
+    /// [`download`](struct.Timings.html#structfield.download) time is
+    /// only available after the body has been read to the end.
+    pub fn timings(&self) -> &Timings {
+        &self.timings
+    }
+
+    /// Returns the HTTP version the server responded with.
+    pub fn version(&self) -> HttpVersion {
+        self.version
+    }
+
+    /// Returns the address of the server the response was received
+    /// from, after resolving the host and following redirections.
+    /// `None` if the socket could not report it.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Returns the local address of the connection the response was
+    /// received on. `None` if the socket could not report it.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Returns the amount of body bytes read so far, after removing
+    /// the transfer encoding. Once the body has been read to the end,
+    /// this is the length of the whole body, regardless of whether
+    /// the server sent a `Content-Length` header.
+    pub fn body_len(&self) -> usize {
+        self.body_len
+    }
+
+    /// Returns the amount of body bytes received so far, before
+    /// removing the `Content-Encoding`. This is the same as
+    /// [`body_len`](struct.ResponseLazy.html#method.body_len) unless
+    /// the body is being decompressed, see the `compression` feature.
+    pub fn wire_len(&self) -> usize {
+        self.wire_len
+    }
 }
 
-impl Iterator for ResponseLazy {
-    type Item = Result<(u8, usize), Error>;
+impl ResponseLazy {
+    /// Makes sure there are body bytes buffered, and returns how many
+    /// of them can be read from
+    /// [`body_buffer`](#method.body_buffer). 0 means the body has
+    /// been read to the end.
+    fn fill_body(&mut self) -> Result<usize, Error> {
+        #[cfg(feature = "compression")]
+        let length = self.fill_decoded()?;
+        #[cfg(not(feature = "compression"))]
+        let length = self.fill_raw()?;
 
-    fn next(&mut self) -> Option<Self::Item> {
+        match self.options.max_body_size {
+            _ if length == 0 => Ok(0),
+            Some(max_body_size) if self.body_len >= max_body_size => Err(Error::BodyTooLarge),
+            Some(max_body_size) => Ok(length.min(max_body_size - self.body_len)),
+            None => Ok(length),
+        }
+    }
+
+    /// Returns the first `length` buffered body bytes, after
+    /// [`fill_body`](#method.fill_body) returned at least `length`.
+    fn body_buffer(&self, length: usize) -> &[u8] {
+        #[cfg(feature = "compression")]
+        if let Some(decoder) = &self.decoder {
+            return &decoder.buffer()[..length];
+        }
+        &self.stream.buffer()[..length]
+    }
+
+    /// Decodes the body until there are decoded bytes buffered, or
+    /// the body ends, and returns how many there are.
+    #[cfg(feature = "compression")]
+    fn fill_decoded(&mut self) -> Result<usize, Error> {
+        loop {
+            match &self.decoder {
+                None => return self.fill_raw(),
+                Some(decoder) if !decoder.buffer().is_empty() || decoder.is_finished() => {
+                    return Ok(decoder.buffer().len())
+                }
+                Some(_) => {}
+            }
+            let length = self.fill_raw()?;
+            if let Some(decoder) = &mut self.decoder {
+                if length == 0 {
+                    decoder.finish()?;
+                } else {
+                    decoder.decode(
+                        &self.stream.buffer()[..length],
+                        self.wire_len + length,
+                        self.options.max_decompression_ratio,
+                    )?;
+                    self.consume_raw(length);
+                }
+            }
+        }
+    }
+
+    /// Makes sure there are body bytes in the stream's buffer, reading
+    /// the chunk framing in between as needed, and returns how many of
+    /// the buffered bytes belong to the body. 0 means the body has
+    /// been read to the end.
+    fn fill_raw(&mut self) -> Result<usize, Error> {
         use HttpStreamState::*;
-        match self.state {
-            EndOnClose => read_until_closed(&mut self.stream),
-            ContentLength(ref mut length) => read_with_content_length(&mut self.stream, length),
-            Chunked(ref mut expecting_chunks, ref mut length, ref mut content_length) => {
-                read_chunked(
-                    &mut self.stream,
-                    &mut self.headers,
-                    expecting_chunks,
-                    length,
-                    content_length,
-                )
+        let length = loop {
+            match self.state {
+                EndOnClose => break self.stream.fill_buf()?.len(),
+                ContentLength(0) | Done => break 0,
+                ContentLength(remaining) | ChunkData(remaining) => {
+                    let available = self.stream.fill_buf()?.len();
+                    if available == 0 {
+                        return Err(Error::IoError(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "the connection was closed before the end of the body",
+                        )));
+                    }
+                    break available.min(remaining);
+                }
+                ChunkSize => {
+                    let length_line = read_text_line(&mut self.stream, &self.options)?;
+                    // Ignore chunk extensions, if any.
+                    let length = length_line.split(';').next().unwrap_or("").trim();
+                    self.state = match usize::from_str_radix(length, 16) {
+                        Ok(0) => {
+                            self.trailers = read_fields(&mut self.stream, &self.options)?;
+                            Done
+                        }
+                        Ok(length) => ChunkData(length),
+                        Err(_) => return Err(Error::MalformedChunkLength),
+                    };
+                }
+                ChunkEnd => {
+                    // Read the trailing \r\n of the chunk.
+                    read_line(&mut self.stream, &self.options)?;
+                    self.state = ChunkSize;
+                }
+            }
+        };
+
+        if length == 0 {
+            // The server closed the connection, or the body ended.
+            self.state = Done;
+            if self.timings.download.is_none() {
+                self.timings.download = Some(self.head_read_at.elapsed());
             }
         }
+        Ok(length)
     }
-}
 
-fn read_until_closed(bytes: &mut HttpStreamBytes) -> Option<<ResponseLazy as Iterator>::Item> {
-    if let Some(byte) = bytes.next() {
-        match byte {
-            Ok(byte) => Some(Ok((byte, 1))),
-            Err(err) => Some(Err(Error::IoError(err))),
+    /// Consumes `amt` bytes of the body from the stream's buffer, and
+    /// returns how many could be consumed.
+    fn consume_raw(&mut self, amt: usize) -> usize {
+        use HttpStreamState::*;
+        let amt = match self.state {
+            ContentLength(remaining) | ChunkData(remaining) => amt.min(remaining),
+            EndOnClose => amt,
+            _ => 0,
+        };
+        self.stream.consume(amt);
+        self.wire_len += amt;
+        match self.state {
+            ContentLength(ref mut remaining) => *remaining -= amt,
+            ChunkData(ref mut remaining) => {
+                *remaining -= amt;
+                if *remaining == 0 {
+                    self.state = ChunkEnd;
+                }
+            }
+            _ => {}
         }
-    } else {
-        None
+        amt
     }
-}
 
-fn read_with_content_length(
-    bytes: &mut HttpStreamBytes,
-    content_length: &mut usize,
-) -> Option<<ResponseLazy as Iterator>::Item> {
-    if *content_length > 0 {
-        *content_length -= 1;
-
-        if let Some(byte) = bytes.next() {
-            match byte {
-                Ok(byte) => return Some(Ok((byte, *content_length + 1))),
-                Err(err) => return Some(Err(Error::IoError(err))),
+    /// Returns how many more body bytes are expected, as far as the
+    /// framing of the body tells.
+    fn expected_len(&self) -> usize {
+        #[cfg(feature = "compression")]
+        if let Some(decoder) = &self.decoder {
+            return decoder.buffer().len().max(1);
+        }
+        match self.state {
+            HttpStreamState::ContentLength(remaining) | HttpStreamState::ChunkData(remaining) => {
+                remaining
             }
+            _ => 1,
         }
     }
-    None
 }
 
-fn read_trailers(
-    bytes: &mut HttpStreamBytes,
-    headers: &mut HashMap<String, String>,
-) -> Result<(), Error> {
-    loop {
-        let trailer_line = read_line(bytes)?;
-        if let Some((header, value)) = parse_header(trailer_line) {
-            headers.insert(header, value);
-        } else {
-            break;
-        }
+impl Read for ResponseLazy {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let length = available.len().min(buf.len());
+        buf[..length].copy_from_slice(&available[..length]);
+        self.consume(length);
+        Ok(length)
     }
-    Ok(())
 }
 
-fn read_chunked(
-    bytes: &mut HttpStreamBytes,
-    headers: &mut HashMap<String, String>,
-    expecting_more_chunks: &mut bool,
-    chunk_length: &mut usize,
-    content_length: &mut usize,
-) -> Option<<ResponseLazy as Iterator>::Item> {
-    if !*expecting_more_chunks && *chunk_length == 0 {
-        return None;
-    }
-
-    if *chunk_length == 0 {
-        // Get the size of the next chunk
-        let length_line = match read_line(bytes) {
-            Ok(line) => line,
-            Err(err) => return Some(Err(err)),
-        };
-        match usize::from_str_radix(&length_line, 16) {
-            Ok(incoming_length) => {
-                if incoming_length == 0 {
-                    if let Err(err) = read_trailers(bytes, headers) {
-                        return Some(Err(err));
-                    }
+impl BufRead for ResponseLazy {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let length = self.fill_body()?;
+        Ok(self.body_buffer(length))
+    }
 
-                    *expecting_more_chunks = false;
-                    headers.insert("content-length".to_string(), (*content_length).to_string());
-                    headers.remove("transfer-encoding");
-                    return None;
-                }
-                *chunk_length = incoming_length;
-                *content_length += incoming_length;
-            }
-            Err(_) => return Some(Err(Error::MalformedChunkLength)),
+    fn consume(&mut self, amt: usize) {
+        #[cfg(feature = "compression")]
+        if let Some(decoder) = &mut self.decoder {
+            self.body_len += decoder.consume(amt);
+            return;
         }
+        self.body_len += self.consume_raw(amt);
     }
+}
 
-    if *chunk_length > 0 {
-        *chunk_length -= 1;
-        if let Some(byte) = bytes.next() {
-            match byte {
-                Ok(byte) => {
-                    // If we're at the end of the chunk...
-                    if *chunk_length == 0 {
-                        //...read the trailing \r\n of the chunk, and
-                        // possibly return an error instead.
-
-                        // TODO: Maybe this could be written in a way
-                        // that doesn't discard the last ok byte if
-                        // the \r\n reading fails?
-                        if let Err(err) = read_line(bytes) {
-                            return Some(Err(err));
-                        }
-                    }
+impl Iterator for ResponseLazy {
+    type Item = Result<(u8, usize), Error>;
 
-                    return Some(Ok((byte, *chunk_length + 1)));
-                }
-                Err(err) => return Some(Err(Error::IoError(err))),
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.fill_body() {
+            Ok(0) => None,
+            Ok(_) => {
+                let byte = self.body_buffer(1)[0];
+                let expected_len = self.expected_len();
+                self.consume(1);
+                Some(Ok((byte, expected_len)))
             }
+            Err(err) => Some(Err(err)),
         }
     }
-
-    None
 }
 
 enum HttpStreamState {
@@ -323,14 +970,19 @@ enum HttpStreamState {
     // read unti lthe server closes the connection (this should be the
     // fallback, if I read the rfc right).
     EndOnClose,
-    // Content-Length was specified, read that amount of bytes
+    // Content-Length was specified, this many bytes are left to read
     ContentLength(usize),
-    // Transfer-Encoding == chunked, so we need to save two pieces of
-    // information: are we expecting more chunks, how much is there
-    // left of the current chunk, and how much have we read? The last
-    // number is needed in order to provide an accurate Content-Length
-    // header after loading all the bytes.
-    Chunked(bool, usize, usize),
+    // Transfer-Encoding == chunked, and the next line is the size of
+    // the next chunk
+    ChunkSize,
+    // Transfer-Encoding == chunked, and this many bytes are left of
+    // the current chunk
+    ChunkData(usize),
+    // Transfer-Encoding == chunked, and the next line is the \r\n
+    // after the data of the chunk
+    ChunkEnd,
+    // The whole body has been read
+    Done,
 }
 
 // This struct is just used in the Response and ResponseLazy
@@ -338,48 +990,53 @@ enum HttpStreamState {
 // reasons. (Eg. response.status_code is much cleaner than
 // response.meta.status_code or similar.)
 struct ResponseMetadata {
-    status_code: i32,
+    version: HttpVersion,
+    status_code: StatusCode,
     reason_phrase: String,
-    headers: HashMap<String, String>,
+    headers: Headers,
     state: HttpStreamState,
 }
 
-fn read_metadata(stream: &mut HttpStreamBytes) -> Result<ResponseMetadata, Error> {
-    let (status_code, reason_phrase) = parse_status_line(&read_line(stream)?)?;
+fn read_metadata(
+    stream: &mut HttpStreamReader,
+    options: &ParseOptions,
+) -> Result<ResponseMetadata, Error> {
+    let status_line = read_text_line(stream, options)?;
+    let (version, status_code, reason_phrase) = parse_status_line(&status_line)?;
+    let headers = read_fields(stream, options)?;
 
-    let mut headers = HashMap::new();
-    loop {
-        let line = read_line(stream)?;
-        if line.is_empty() {
-            // Body starts here
-            break;
-        }
-        if let Some(header) = parse_header(line) {
-            headers.insert(header.0, header.1);
-        }
-    }
-
-    let mut chunked = false;
+    // The body is chunked if chunked is the last of the codings, in
+    // the order they were applied, over all the fields.
+    let last_coding = headers
+        .get_all("transfer-encoding")
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|coding| !coding.is_empty())
+        .last();
+    // Repeated Content-Length values, or fields, must all agree, or
+    // the framing of the body is ambiguous.
     let mut content_length = None;
-    for (header, value) in &headers {
-        // Handle the Transfer-Encoding header
-        if header.to_lowercase().trim() == "transfer-encoding"
-            && value.to_lowercase().trim() == "chunked"
-        {
-            chunked = true;
-        }
-
-        // Handle the Content-Length header
-        if header.to_lowercase().trim() == "content-length" {
-            match str::parse::<usize>(value.trim()) {
-                Ok(length) => content_length = Some(length),
-                Err(_) => return Err(Error::MalformedContentLength),
-            }
+    for value in headers
+        .get_all("content-length")
+        .flat_map(|value| value.split(','))
+    {
+        let length = match str::parse::<usize>(value.trim()) {
+            Ok(length) => length,
+            Err(_) => return Err(Error::MalformedContentLength),
+        };
+        if content_length.is_some_and(|first| first != length) {
+            return Err(Error::MalformedContentLength);
         }
+        content_length = Some(length);
     }
 
-    let state = if chunked {
-        HttpStreamState::Chunked(true, 0, 0)
+    let state = if last_coding.is_some_and(|coding| coding.eq_ignore_ascii_case("chunked")) {
+        HttpStreamState::ChunkSize
+    } else if last_coding.is_some() {
+        // Other transfer codings can only be ended by closing the
+        // connection, and override the Content-Length (RFC 9112,
+        // section 6.3).
+        HttpStreamState::EndOnClose
     } else if let Some(length) = content_length {
         HttpStreamState::ContentLength(length)
     } else {
@@ -387,6 +1044,7 @@ fn read_metadata(stream: &mut HttpStreamBytes) -> Result<ResponseMetadata, Error
     };
 
     Ok(ResponseMetadata {
+        version,
         status_code,
         reason_phrase,
         headers,
@@ -394,43 +1052,200 @@ fn read_metadata(stream: &mut HttpStreamBytes) -> Result<ResponseMetadata, Error
     })
 }
 
-fn read_line(stream: &mut HttpStreamBytes) -> Result<String, Error> {
-    let mut bytes = Vec::with_capacity(32);
-    for byte in stream {
-        let byte = byte?;
-        if byte == b'\n' {
-            // Pop the \r off, as HTTP lines end in \r\n.
-            bytes.pop();
+/// Keeps track of how many header fields, and how many bytes of
+/// them, have been read so far, to enforce the header options.
+struct HeaderBudget {
+    count: usize,
+    size: usize,
+    max_count: Option<usize>,
+    max_size: Option<usize>,
+}
+
+impl HeaderBudget {
+    fn new(options: &ParseOptions) -> HeaderBudget {
+        HeaderBudget {
+            count: 0,
+            size: 0,
+            max_count: options.max_header_count,
+            max_size: options.max_headers_size,
+        }
+    }
+
+    /// Counts `fields` new fields, spanning `line_length` bytes
+    /// (excluding the line ending).
+    fn spend(&mut self, fields: usize, line_length: usize) -> Result<(), Error> {
+        self.count += fields;
+        self.size += line_length + 2;
+        match (self.max_count, self.max_size) {
+            (Some(max_count), _) if self.count > max_count => Err(Error::TooManyHeaders),
+            (_, Some(max_size)) if self.size > max_size => Err(Error::HeadersTooLarge),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reads header or trailer fields until the empty line that ends
+/// them.
+fn read_fields(stream: &mut HttpStreamReader, options: &ParseOptions) -> Result<Headers, Error> {
+    let mut fields = Headers::new();
+    let mut budget = HeaderBudget::new(options);
+    // Whether the last line was a field that a folded line can continue.
+    let mut foldable = false;
+    loop {
+        let line = read_line(stream, options)?;
+        if line.is_empty() {
             break;
-        } else {
-            bytes.push(byte);
         }
+
+        if line[0] == b' ' || line[0] == b'\t' {
+            // obs-fold: this line continues the previous field's value.
+            if options.mode == ParseMode::Strict {
+                return Err(Error::ObsoleteLineFolding);
+            }
+            budget.spend(0, line.len())?;
+            let continuation = decode_line(line, options.mode)?;
+            if let Some(value) = fields.last_value_mut().filter(|_| foldable) {
+                value.push(' ');
+                value.push_str(continuation.trim());
+            }
+            continue;
+        }
+
+        budget.spend(1, line.len())?;
+        let line = decode_line(line, options.mode)?;
+        foldable = match parse_header(line) {
+            // RFC 9112, section 5.1: no whitespace is allowed between
+            // the field name and the colon.
+            Some((header, _))
+                if options.mode == ParseMode::Strict && header.ends_with([' ', '\t']) =>
+            {
+                return Err(Error::MalformedHeader);
+            }
+            Some((mut header, value)) => {
+                header.truncate(header.trim_end_matches([' ', '\t']).len());
+                fields.append(header, value);
+                true
+            }
+            None if options.mode == ParseMode::Strict => return Err(Error::MalformedHeader),
+            None => false,
+        };
     }
-    String::from_utf8(bytes).map_err(|_error| Error::InvalidUtf8InResponse)
+    Ok(fields)
 }
 
-fn parse_status_line(line: &str) -> Result<(i32, String), Error> {
+/// Reads a line, and returns it without the line ending.
+fn read_line(stream: &mut HttpStreamReader, options: &ParseOptions) -> Result<Vec<u8>, Error> {
+    // Leave room for the trailing \r.
+    let max_length = options
+        .max_line_length
+        .map_or(usize::MAX, |max| max.saturating_add(1));
+    let mut bytes = Vec::new();
+    loop {
+        let available = stream.fill_buf()?;
+        if available.is_empty() {
+            // The connection was closed before the end of the line.
+            return Ok(bytes);
+        }
+        let (line_end, consumed) = match available.iter().position(|&b| b == b'\n') {
+            Some(index) => (Some(index), index + 1),
+            None => (None, available.len()),
+        };
+        let length = line_end.unwrap_or(available.len());
+        if bytes.len() + length > max_length {
+            return Err(Error::LineTooLong);
+        }
+        bytes.extend_from_slice(&available[..length]);
+        stream.consume(consumed);
+
+        if line_end.is_some() {
+            // Pop the \r off, as HTTP lines end in \r\n.
+            if bytes.last() == Some(&b'\r') {
+                bytes.pop();
+            } else if options.mode == ParseMode::Strict {
+                return Err(Error::BareLineFeed);
+            }
+            return Ok(bytes);
+        }
+    }
+}
+
+fn read_text_line(stream: &mut HttpStreamReader, options: &ParseOptions) -> Result<String, Error> {
+    decode_line(read_line(stream, options)?, options.mode)
+}
+
+fn decode_line(bytes: Vec<u8>, mode: ParseMode) -> Result<String, Error> {
+    match String::from_utf8(bytes) {
+        Ok(line) => Ok(line),
+        // ISO-8859-1 maps each byte to the code point of the same value.
+        Err(err) if mode == ParseMode::Lenient => {
+            Ok(err.into_bytes().into_iter().map(char::from).collect())
+        }
+        Err(_) => Err(Error::InvalidUtf8InResponse),
+    }
+}
+
+fn decode_form_component(s: &str) -> Result<String, Error> {
+    match urlencoding::decode(&s.replace('+', " ")) {
+        Ok(decoded) => Ok(decoded.into_owned()),
+        Err(err) => Err(Error::InvalidUtf8InBody(err.utf8_error())),
+    }
+}
+
+/// Makes sure that the media type of the `Content-Type` header is one
+/// that `accepts` the format named by `expected`.
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+fn check_content_type(
+    headers: &Headers,
+    expected: &'static str,
+    accepts: fn(&str) -> bool,
+) -> Result<(), Error> {
+    match headers.content_type() {
+        Some(content_type) if accepts(content_type.essence()) => Ok(()),
+        _ => Err(Error::UnexpectedContentType {
+            expected,
+            content_type: headers.get("content-type").map(str::to_string),
+        }),
+    }
+}
+
+fn parse_status_line(line: &str) -> Result<(HttpVersion, StatusCode, String), Error> {
     // sample status line format
     // HTTP/1.1 200 OK
     let mut parts = line.splitn(3, ' ');
-    let _http_version = parts.next();
+    let version = match parts
+        .next()
+        .and_then(|version| version.strip_prefix("HTTP/"))
+    {
+        Some("1.0") => HttpVersion::Http10,
+        Some(version) if version.len() == 3 && version.starts_with("1.") => {
+            match version.as_bytes()[2] {
+                b'0'..=b'9' => HttpVersion::Http11,
+                _ => return Err(Error::UnsupportedHttpVersion),
+            }
+        }
+        Some(_) => return Err(Error::UnsupportedHttpVersion),
+        None => return Err(Error::MalformedStatusLine),
+    };
     let status_code = parts.next().ok_or(Error::MalformedStatusLine)?;
     let reason_phrase = parts.next().unwrap_or("");
 
     let status_code = status_code
-        .parse::<i32>()
-        .map_err(|_| Error::MalformedStatusLine)?;
+        .parse::<u16>()
+        .ok()
+        .filter(|_| status_code.len() == 3)
+        .and_then(StatusCode::from_u16)
+        .ok_or(Error::MalformedStatusLine)?;
     let reason_phrase = if reason_phrase.is_empty() {
         "Server did not provide a reason".to_string()
     } else {
         reason_phrase.to_string()
     };
 
-    Ok((status_code, reason_phrase))
+    Ok((version, status_code, reason_phrase))
 }
 
 fn parse_header(mut line: String) -> Option<(String, String)> {
-    if let Some(location) = line.find(':') {
+    if let Some(location) = line.find(':').filter(|&location| location > 0) {
         // Trim the first character of the header if it is a space,
         // otherwise return everything after the ':'. This should
         // preserve the behavior in versions <=2.0.1 in most cases
@@ -447,8 +1262,6 @@ fn parse_header(mut line: String) -> Option<(String, String)> {
         };
 
         line.truncate(location);
-        // Headers should be ascii, I'm pretty sure. If not, please open an issue.
-        line.make_ascii_lowercase();
         return Some((line, value));
     }
     None
@@ -456,20 +1269,21 @@ fn parse_header(mut line: String) -> Option<(String, String)> {
 
 #[cfg(test)]
 mod tests {
-    use super::parse_status_line;
+    use super::{parse_status_line, HttpVersion};
     use crate::Error;
 
     #[test]
     fn parses_status_line() {
-        let (status_code, reason_phrase) = parse_status_line("HTTP/1.1 200 OK").unwrap();
+        let (version, status_code, reason_phrase) = parse_status_line("HTTP/1.1 200 OK").unwrap();
 
+        assert_eq!(version, HttpVersion::Http11);
         assert_eq!(status_code, 200);
         assert_eq!(reason_phrase, "OK");
     }
 
     #[test]
     fn handles_empty_reason_phrase() {
-        let (status_code, reason_phrase) = parse_status_line("HTTP/1.1 204").unwrap();
+        let (_, status_code, reason_phrase) = parse_status_line("HTTP/1.1 204").unwrap();
 
         assert_eq!(status_code, 204);
         assert_eq!(reason_phrase, "Server did not provide a reason");
@@ -482,4 +1296,35 @@ mod tests {
             other => panic!("expected malformed status line, got {:?}", other),
         }
     }
+
+    #[test]
+    fn parses_http_versions() {
+        let version = |line| parse_status_line(line).map(|(version, _, _)| version);
+
+        assert!(matches!(
+            version("HTTP/1.0 200 OK"),
+            Ok(HttpVersion::Http10)
+        ));
+        assert!(matches!(
+            version("HTTP/1.2 200 OK"),
+            Ok(HttpVersion::Http11)
+        ));
+        for line in ["HTTP/2 200 OK", "HTTP/0.9 200 OK", "HTTP/1.x 200 OK"] {
+            assert!(matches!(version(line), Err(Error::UnsupportedHttpVersion)));
+        }
+        assert!(matches!(
+            version("ICY 200 OK"),
+            Err(Error::MalformedStatusLine)
+        ));
+    }
+
+    #[test]
+    fn rejects_status_codes_that_are_not_three_digits() {
+        for line in ["HTTP/1.1 20 OK", "HTTP/1.1 2000 OK", "HTTP/1.1 +20 OK"] {
+            assert!(matches!(
+                parse_status_line(line),
+                Err(Error::MalformedStatusLine)
+            ));
+        }
+    }
 }
@@ -0,0 +1,296 @@
+use std::fmt;
+
+/// An HTTP status code, eg. 404.
+///
+/// Status codes are always three digits, ie. between 100 and 999.
+/// They can be compared to plain integers, and the constants on this
+/// type name the codes defined in RFC 9110 and its extensions.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), tinyget::Error> {
+/// use tinyget::StatusCode;
+///
+/// let response = tinyget::get("http://example.com").send()?;
+/// if response.status_code == StatusCode::NOT_FOUND {
+///     println!("not found");
+/// } else if response.status_code.is_server_error() {
+///     println!("server error: {}", response.status_code);
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StatusCode(u16);
+
+impl StatusCode {
+    /// 100 Continue
+    pub const CONTINUE: StatusCode = StatusCode(100);
+    /// 101 Switching Protocols
+    pub const SWITCHING_PROTOCOLS: StatusCode = StatusCode(101);
+    /// 103 Early Hints
+    pub const EARLY_HINTS: StatusCode = StatusCode(103);
+    /// 200 OK
+    pub const OK: StatusCode = StatusCode(200);
+    /// 201 Created
+    pub const CREATED: StatusCode = StatusCode(201);
+    /// 202 Accepted
+    pub const ACCEPTED: StatusCode = StatusCode(202);
+    /// 203 Non-Authoritative Information
+    pub const NON_AUTHORITATIVE_INFORMATION: StatusCode = StatusCode(203);
+    /// 204 No Content
+    pub const NO_CONTENT: StatusCode = StatusCode(204);
+    /// 205 Reset Content
+    pub const RESET_CONTENT: StatusCode = StatusCode(205);
+    /// 206 Partial Content
+    pub const PARTIAL_CONTENT: StatusCode = StatusCode(206);
+    /// 300 Multiple Choices
+    pub const MULTIPLE_CHOICES: StatusCode = StatusCode(300);
+    /// 301 Moved Permanently
+    pub const MOVED_PERMANENTLY: StatusCode = StatusCode(301);
+    /// 302 Found
+    pub const FOUND: StatusCode = StatusCode(302);
+    /// 303 See Other
+    pub const SEE_OTHER: StatusCode = StatusCode(303);
+    /// 304 Not Modified
+    pub const NOT_MODIFIED: StatusCode = StatusCode(304);
+    /// 307 Temporary Redirect
+    pub const TEMPORARY_REDIRECT: StatusCode = StatusCode(307);
+    /// 308 Permanent Redirect
+    pub const PERMANENT_REDIRECT: StatusCode = StatusCode(308);
+    /// 400 Bad Request
+    pub const BAD_REQUEST: StatusCode = StatusCode(400);
+    /// 401 Unauthorized
+    pub const UNAUTHORIZED: StatusCode = StatusCode(401);
+    /// 402 Payment Required
+    pub const PAYMENT_REQUIRED: StatusCode = StatusCode(402);
+    /// 403 Forbidden
+    pub const FORBIDDEN: StatusCode = StatusCode(403);
+    /// 404 Not Found
+    pub const NOT_FOUND: StatusCode = StatusCode(404);
+    /// 405 Method Not Allowed
+    pub const METHOD_NOT_ALLOWED: StatusCode = StatusCode(405);
+    /// 406 Not Acceptable
+    pub const NOT_ACCEPTABLE: StatusCode = StatusCode(406);
+    /// 407 Proxy Authentication Required
+    pub const PROXY_AUTHENTICATION_REQUIRED: StatusCode = StatusCode(407);
+    /// 408 Request Timeout
+    pub const REQUEST_TIMEOUT: StatusCode = StatusCode(408);
+    /// 409 Conflict
+    pub const CONFLICT: StatusCode = StatusCode(409);
+    /// 410 Gone
+    pub const GONE: StatusCode = StatusCode(410);
+    /// 411 Length Required
+    pub const LENGTH_REQUIRED: StatusCode = StatusCode(411);
+    /// 412 Precondition Failed
+    pub const PRECONDITION_FAILED: StatusCode = StatusCode(412);
+    /// 413 Content Too Large
+    pub const CONTENT_TOO_LARGE: StatusCode = StatusCode(413);
+    /// 414 URI Too Long
+    pub const URI_TOO_LONG: StatusCode = StatusCode(414);
+    /// 415 Unsupported Media Type
+    pub const UNSUPPORTED_MEDIA_TYPE: StatusCode = StatusCode(415);
+    /// 416 Range Not Satisfiable
+    pub const RANGE_NOT_SATISFIABLE: StatusCode = StatusCode(416);
+    /// 417 Expectation Failed
+    pub const EXPECTATION_FAILED: StatusCode = StatusCode(417);
+    /// 418 I'm a teapot
+    pub const IM_A_TEAPOT: StatusCode = StatusCode(418);
+    /// 421 Misdirected Request
+    pub const MISDIRECTED_REQUEST: StatusCode = StatusCode(421);
+    /// 422 Unprocessable Content
+    pub const UNPROCESSABLE_CONTENT: StatusCode = StatusCode(422);
+    /// 425 Too Early
+    pub const TOO_EARLY: StatusCode = StatusCode(425);
+    /// 426 Upgrade Required
+    pub const UPGRADE_REQUIRED: StatusCode = StatusCode(426);
+    /// 428 Precondition Required
+    pub const PRECONDITION_REQUIRED: StatusCode = StatusCode(428);
+    /// 429 Too Many Requests
+    pub const TOO_MANY_REQUESTS: StatusCode = StatusCode(429);
+    /// 431 Request Header Fields Too Large
+    pub const REQUEST_HEADER_FIELDS_TOO_LARGE: StatusCode = StatusCode(431);
+    /// 451 Unavailable For Legal Reasons
+    pub const UNAVAILABLE_FOR_LEGAL_REASONS: StatusCode = StatusCode(451);
+    /// 500 Internal Server Error
+    pub const INTERNAL_SERVER_ERROR: StatusCode = StatusCode(500);
+    /// 501 Not Implemented
+    pub const NOT_IMPLEMENTED: StatusCode = StatusCode(501);
+    /// 502 Bad Gateway
+    pub const BAD_GATEWAY: StatusCode = StatusCode(502);
+    /// 503 Service Unavailable
+    pub const SERVICE_UNAVAILABLE: StatusCode = StatusCode(503);
+    /// 504 Gateway Timeout
+    pub const GATEWAY_TIMEOUT: StatusCode = StatusCode(504);
+    /// 505 HTTP Version Not Supported
+    pub const HTTP_VERSION_NOT_SUPPORTED: StatusCode = StatusCode(505);
+    /// 511 Network Authentication Required
+    pub const NETWORK_AUTHENTICATION_REQUIRED: StatusCode = StatusCode(511);
+
+    /// Returns the status code for `code`, or `None` if it is not
+    /// three digits long.
+    pub fn from_u16(code: u16) -> Option<StatusCode> {
+        if (100..1000).contains(&code) {
+            Some(StatusCode(code))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the status code as a number.
+    pub fn as_u16(self) -> u16 {
+        self.0
+    }
+
+    /// Returns `true` for 1xx status codes.
+    pub fn is_informational(self) -> bool {
+        (100..200).contains(&self.0)
+    }
+
+    /// Returns `true` for 2xx status codes.
+    pub fn is_success(self) -> bool {
+        (200..300).contains(&self.0)
+    }
+
+    /// Returns `true` for 3xx status codes.
+    pub fn is_redirection(self) -> bool {
+        (300..400).contains(&self.0)
+    }
+
+    /// Returns `true` for 4xx status codes.
+    pub fn is_client_error(self) -> bool {
+        (400..500).contains(&self.0)
+    }
+
+    /// Returns `true` for 5xx status codes.
+    pub fn is_server_error(self) -> bool {
+        (500..600).contains(&self.0)
+    }
+
+    /// Returns the reason phrase the specification gives the status
+    /// code, eg. "Not Found" for 404, or `None` if it is not a known
+    /// status code. Servers may send a different reason phrase, which
+    /// is available in
+    /// [`Response::reason_phrase`](struct.Response.html#structfield.reason_phrase).
+    pub fn canonical_reason(self) -> Option<&'static str> {
+        Some(match self.0 {
+            100 => "Continue",
+            101 => "Switching Protocols",
+            103 => "Early Hints",
+            200 => "OK",
+            201 => "Created",
+            202 => "Accepted",
+            203 => "Non-Authoritative Information",
+            204 => "No Content",
+            205 => "Reset Content",
+            206 => "Partial Content",
+            300 => "Multiple Choices",
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            304 => "Not Modified",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            402 => "Payment Required",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            407 => "Proxy Authentication Required",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            410 => "Gone",
+            411 => "Length Required",
+            412 => "Precondition Failed",
+            413 => "Content Too Large",
+            414 => "URI Too Long",
+            415 => "Unsupported Media Type",
+            416 => "Range Not Satisfiable",
+            417 => "Expectation Failed",
+            418 => "I'm a teapot",
+            421 => "Misdirected Request",
+            422 => "Unprocessable Content",
+            425 => "Too Early",
+            426 => "Upgrade Required",
+            428 => "Precondition Required",
+            429 => "Too Many Requests",
+            431 => "Request Header Fields Too Large",
+            451 => "Unavailable For Legal Reasons",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            505 => "HTTP Version Not Supported",
+            511 => "Network Authentication Required",
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Debug for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<StatusCode> for u16 {
+    fn from(status: StatusCode) -> u16 {
+        status.0
+    }
+}
+
+impl From<StatusCode> for i32 {
+    fn from(status: StatusCode) -> i32 {
+        i32::from(status.0)
+    }
+}
+
+impl PartialEq<u16> for StatusCode {
+    fn eq(&self, other: &u16) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<i32> for StatusCode {
+    fn eq(&self, other: &i32) -> bool {
+        i32::from(self.0) == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StatusCode;
+
+    #[test]
+    fn classifies_status_codes() {
+        assert!(StatusCode::CONTINUE.is_informational());
+        assert!(StatusCode::NO_CONTENT.is_success());
+        assert!(StatusCode::SEE_OTHER.is_redirection());
+        assert!(StatusCode::IM_A_TEAPOT.is_client_error());
+        assert!(StatusCode::BAD_GATEWAY.is_server_error());
+        assert!(!StatusCode::from_u16(600).unwrap().is_server_error());
+    }
+
+    #[test]
+    fn rejects_codes_that_are_not_three_digits() {
+        assert_eq!(StatusCode::from_u16(99), None);
+        assert_eq!(StatusCode::from_u16(1000), None);
+        assert_eq!(StatusCode::from_u16(200), Some(StatusCode::OK));
+    }
+
+    #[test]
+    fn has_canonical_reasons() {
+        assert_eq!(StatusCode::NOT_FOUND.canonical_reason(), Some("Not Found"));
+        assert_eq!(StatusCode::from_u16(299).unwrap().canonical_reason(), None);
+        assert_eq!(StatusCode::OK, 200);
+    }
+}
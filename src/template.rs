@@ -0,0 +1,125 @@
+use crate::Error;
+
+/// Expands the `{var}`, `{+var}` and `{#var}` expressions in
+/// `template` with the values in `vars`.
+///
+/// Simple expansion (`{var}`) percent-encodes every character except
+/// the unreserved ones, so values can't escape their path segment or
+/// query component. Reserved expansion (`{+var}`) and fragment
+/// expansion (`{#var}`) also leave reserved characters and existing
+/// percent-encoded triplets as-is. Undefined variables expand to
+/// nothing.
+pub(crate) fn expand(template: &str, vars: &[(String, String)]) -> Result<String, Error> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err(Error::MalformedTemplate);
+        }
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let end = rest.find('}').ok_or(Error::MalformedTemplate)?;
+        expand_expression(&rest[..end], vars, &mut expanded)?;
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn expand_expression(
+    expression: &str,
+    vars: &[(String, String)],
+    expanded: &mut String,
+) -> Result<(), Error> {
+    let (prefix, allow_reserved, name) = match expression.as_bytes().first() {
+        Some(b'+') => ("", true, &expression[1..]),
+        Some(b'#') => ("#", true, &expression[1..]),
+        _ => ("", false, expression),
+    };
+    if !is_varname(name) {
+        return Err(Error::MalformedTemplate);
+    }
+
+    if let Some((_, value)) = vars.iter().find(|(var, _)| var == name) {
+        expanded.push_str(prefix);
+        if allow_reserved {
+            encode_reserved(value, expanded);
+        } else {
+            expanded.push_str(&urlencoding::encode(value));
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` if `name` is a valid RFC 6570 varname. This also
+/// rejects the operators of levels 3 and 4, which are not supported.
+fn is_varname(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'.')
+}
+
+/// Percent-encodes `value` into `expanded`, except for unreserved and
+/// reserved characters, and existing percent-encoded triplets.
+fn encode_reserved(value: &str, expanded: &mut String) {
+    let bytes = value.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        let is_triplet = b == b'%'
+            && bytes.len() > i + 2
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit();
+        if b.is_ascii_alphanumeric() || b"-._~:/?#[]@!$&'()*+,;=".contains(&b) || is_triplet {
+            expanded.push(b as char);
+        } else {
+            expanded.push_str(&format!("%{:02X}", b));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+    use crate::Error;
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn expands_simple_strings() {
+        let vars = vars(&[("owner", "a/b"), ("repo", "x?y z"), ("n", "42")]);
+        assert_eq!(
+            expand("http://example.com/repos/{owner}/{repo}/issues/{n}", &vars).unwrap(),
+            "http://example.com/repos/a%2Fb/x%3Fy%20z/issues/42"
+        );
+    }
+
+    #[test]
+    fn expands_reserved_and_fragment() {
+        let vars = vars(&[("path", "/foo/bar baz"), ("hash", "sec%201"), ("x", "é")]);
+        assert_eq!(
+            expand("http://example.com{+path}{#hash}", &vars).unwrap(),
+            "http://example.com/foo/bar%20baz#sec%201"
+        );
+        assert_eq!(expand("{+x}", &vars).unwrap(), "%C3%A9");
+    }
+
+    #[test]
+    fn omits_undefined_variables() {
+        assert_eq!(expand("/a/{b}{#c}", &[]).unwrap(), "/a/");
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        for template in ["/{a", "/a}", "/{}", "/{a,b}", "/{?a}", "/{/a}"] {
+            assert!(matches!(
+                expand(template, &[]),
+                Err(Error::MalformedTemplate)
+            ));
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+/// How long each phase of a request took.
+///
+/// Available from [`Response::timings`](struct.Response.html#method.timings)
+/// and [`ResponseLazy::timings`](struct.ResponseLazy.html#method.timings).
+/// The phases happen one after another, in the order of the fields.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), tinyget::Error> {
+/// let response = tinyget::get("http://example.com").send()?;
+/// let timings = response.timings();
+/// println!("dns: {:?}", timings.dns);
+/// println!("time to first byte: {:?}", timings.first_byte);
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Timings {
+    /// Resolving the host into addresses.
+    pub dns: Duration,
+    /// Establishing the TCP connection, including the attempts on
+    /// addresses that refused it.
+    pub connect: Duration,
+    /// The TLS handshake, or `None` if the request was not sent over
+    /// HTTPS.
+    pub tls_handshake: Option<Duration>,
+    /// Writing the request into the connection.
+    pub request_write: Duration,
+    /// Waiting for the first byte of the response, after the request
+    /// was written.
+    pub first_byte: Duration,
+    /// Reading the body, after the response's head was read. `None`
+    /// until the body has been read to the end.
+    pub download: Option<Duration>,
+    /// The timings of the redirections that were followed before this
+    /// response, in order. Their `download` is always `None`, as the
+    /// bodies of redirections are not read.
+    pub redirects: Vec<Timings>,
+    /// The timings of the first attempt at this request, if it was
+    /// answered with `417 Expectation Failed` and sent again, see
+    /// [`Request::with_expect_continue`](struct.Request.html#method.with_expect_continue).
+    pub expectation_failed: Option<Box<Timings>>,
+}
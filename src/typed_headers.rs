@@ -0,0 +1,610 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+/// A media type with parameters, as sent in the `Content-Type`
+/// header, eg. `text/html; charset=utf-8`.
+///
+/// The type, subtype and parameter names are case-insensitive, so
+/// they are kept in lowercase. Parameter values are kept as they
+/// are, without quotes.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), tinyget::Error> {
+/// let response = tinyget::get("http://example.com").send()?;
+/// if let Some(content_type) = response.content_type() {
+///     println!("{} in {:?}", content_type.essence(), content_type.charset());
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ContentType {
+    essence: String,
+    params: Vec<(String, String)>,
+}
+
+impl ContentType {
+    /// Creates a content type without parameters from `essence`, eg.
+    /// `"application/json"`.
+    pub fn new(essence: &str) -> ContentType {
+        ContentType {
+            essence: essence.trim().to_ascii_lowercase(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Adds a parameter, eg. `charset=utf-8`. The value is quoted
+    /// when it is sent, if needed.
+    pub fn with_param<T: AsRef<str>, U: Into<String>>(mut self, name: T, value: U) -> ContentType {
+        self.params
+            .push((name.as_ref().to_ascii_lowercase(), value.into()));
+        self
+    }
+
+    /// Parses a `Content-Type` header value. Returns `None` if it is
+    /// not a `type/subtype` followed by `name=value` parameters.
+    /// Parameters without a value are skipped, like browsers do.
+    pub fn parse(value: &str) -> Option<ContentType> {
+        let mut parts = split_outside_quotes(value, ';').into_iter();
+        let essence = parts.next()?.trim();
+        let (main_type, subtype) = essence.split_once('/')?;
+        if !is_token(main_type) || !is_token(subtype) {
+            return None;
+        }
+        let mut content_type = ContentType::new(essence);
+        for param in parts {
+            let Some((name, value)) = param.split_once('=') else {
+                continue;
+            };
+            let name = name.trim();
+            if !is_token(name) {
+                return None;
+            }
+            content_type = content_type.with_param(name, unquote(value.trim())?);
+        }
+        Some(content_type)
+    }
+
+    /// Returns the type and subtype, without the parameters, eg.
+    /// `text/html`.
+    pub fn essence(&self) -> &str {
+        &self.essence
+    }
+
+    /// Returns the type, eg. `text` for `text/html`.
+    pub fn main_type(&self) -> &str {
+        self.essence.split('/').next().unwrap_or("")
+    }
+
+    /// Returns the subtype, eg. `html` for `text/html`, including any
+    /// structured syntax suffix, like `+json`.
+    pub fn subtype(&self) -> &str {
+        self.essence
+            .split_once('/')
+            .map_or("", |(_, subtype)| subtype)
+    }
+
+    /// Returns the value of the first parameter called `name`. The
+    /// name is matched case-insensitively.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the `(name, value)` pairs of the parameters, in order.
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Returns the `charset` parameter.
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.essence)?;
+        for (name, value) in &self.params {
+            write!(f, "; {}={}", name, quote(value))?;
+        }
+        Ok(())
+    }
+}
+
+/// The directives of a `Cache-Control` header, eg.
+/// `max-age=3600, must-revalidate`.
+///
+/// Directive names are case-insensitive, so they are kept in
+/// lowercase. Unknown directives are kept too, and can be read with
+/// [`get`](#method.get).
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), tinyget::Error> {
+/// let response = tinyget::get("http://example.com").send()?;
+/// let cache_control = response.cache_control().unwrap_or_default();
+/// if !cache_control.no_store() {
+///     println!("cacheable for {:?}", cache_control.max_age());
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct CacheControl {
+    directives: Vec<(String, Option<String>)>,
+}
+
+impl CacheControl {
+    /// Creates an empty list of directives.
+    pub fn new() -> CacheControl {
+        CacheControl::default()
+    }
+
+    /// Adds a directive without a value, eg. `no-cache`.
+    pub fn with_directive<T: AsRef<str>>(mut self, name: T) -> CacheControl {
+        self.directives
+            .push((name.as_ref().to_ascii_lowercase(), None));
+        self
+    }
+
+    /// Adds a directive with a value, eg. `max-stale=60`. The value
+    /// is quoted when it is sent, if needed.
+    pub fn with_directive_value<T: AsRef<str>, U: Into<String>>(
+        mut self,
+        name: T,
+        value: U,
+    ) -> CacheControl {
+        self.directives
+            .push((name.as_ref().to_ascii_lowercase(), Some(value.into())));
+        self
+    }
+
+    /// Adds a `max-age` directive, in whole seconds.
+    pub fn with_max_age(self, max_age: Duration) -> CacheControl {
+        self.with_directive_value("max-age", max_age.as_secs().to_string())
+    }
+
+    /// Parses a `Cache-Control` header value. Returns `None` if a
+    /// directive name is not a token, or a quoted value is not
+    /// closed.
+    pub fn parse(value: &str) -> Option<CacheControl> {
+        let mut cache_control = CacheControl::new();
+        for directive in split_outside_quotes(value, ',') {
+            let directive = directive.trim();
+            if directive.is_empty() {
+                continue;
+            }
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(unquote(value.trim())?)),
+                None => (directive, None),
+            };
+            if !is_token(name) {
+                return None;
+            }
+            cache_control
+                .directives
+                .push((name.to_ascii_lowercase(), value));
+        }
+        Some(cache_control)
+    }
+
+    /// Returns whether there is a directive called `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.directives
+            .iter()
+            .any(|(directive, _)| directive.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the value of the first directive called `name`, if it
+    /// has one.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.directives
+            .iter()
+            .find(|(directive, _)| directive.eq_ignore_ascii_case(name))
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// Returns the `(name, value)` pairs of the directives, in order.
+    pub fn directives(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.directives
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_deref()))
+    }
+
+    /// Returns the `max-age` directive. `None` if it is missing or
+    /// not a number of seconds.
+    pub fn max_age(&self) -> Option<Duration> {
+        self.seconds("max-age")
+    }
+
+    /// Returns the `s-maxage` directive, the `max-age` of shared
+    /// caches.
+    pub fn s_maxage(&self) -> Option<Duration> {
+        self.seconds("s-maxage")
+    }
+
+    /// Returns whether there is a `no-cache` directive.
+    pub fn no_cache(&self) -> bool {
+        self.contains("no-cache")
+    }
+
+    /// Returns whether there is a `no-store` directive.
+    pub fn no_store(&self) -> bool {
+        self.contains("no-store")
+    }
+
+    /// Returns whether there is a `must-revalidate` directive.
+    pub fn must_revalidate(&self) -> bool {
+        self.contains("must-revalidate")
+    }
+
+    /// Returns whether there is a `public` directive.
+    pub fn is_public(&self) -> bool {
+        self.contains("public")
+    }
+
+    /// Returns whether there is a `private` directive.
+    pub fn is_private(&self) -> bool {
+        self.contains("private")
+    }
+
+    /// Returns whether there is an `immutable` directive.
+    pub fn is_immutable(&self) -> bool {
+        self.contains("immutable")
+    }
+
+    fn seconds(&self, name: &str) -> Option<Duration> {
+        let value = self.get(name)?;
+        if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        // Too large values mean "forever" (RFC 9111, section 1.2.2).
+        Some(Duration::from_secs(value.parse().unwrap_or(u64::MAX)))
+    }
+}
+
+impl fmt::Display for CacheControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (name, value)) in self.directives.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", name)?;
+            if let Some(value) = value {
+                write!(f, "={}", quote(value))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An entity tag, as sent in the `ETag` header, eg. `"xyzzy"` or
+/// `W/"xyzzy"`, to make conditional requests with
+/// [`Request::with_if_none_match`](struct.Request.html#method.with_if_none_match).
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), tinyget::Error> {
+/// let response = tinyget::get("http://example.com/feed").send()?;
+/// if let Some(etag) = response.etag() {
+///     let response = tinyget::get("http://example.com/feed")
+///         .with_if_none_match([etag])
+///         .send()?;
+///     if response.status_code == tinyget::StatusCode::NOT_MODIFIED {
+///         println!("unchanged");
+///     }
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ETag {
+    tag: String,
+    weak: bool,
+}
+
+impl ETag {
+    /// Creates a strong entity tag from `tag`, without quotes.
+    pub fn strong<T: Into<String>>(tag: T) -> ETag {
+        ETag {
+            tag: tag.into(),
+            weak: false,
+        }
+    }
+
+    /// Creates a weak entity tag from `tag`, without quotes.
+    pub fn weak<T: Into<String>>(tag: T) -> ETag {
+        ETag {
+            tag: tag.into(),
+            weak: true,
+        }
+    }
+
+    /// Parses an `ETag` header value. Returns `None` if it is not a
+    /// quoted tag, optionally prefixed with `W/`.
+    pub fn parse(value: &str) -> Option<ETag> {
+        let value = value.trim();
+        let (weak, quoted) = match value.strip_prefix("W/") {
+            Some(quoted) => (true, quoted),
+            None => (false, value),
+        };
+        let tag = quoted.strip_prefix('"')?.strip_suffix('"')?;
+        if tag.contains('"') {
+            return None;
+        }
+        Some(ETag {
+            tag: tag.to_string(),
+            weak,
+        })
+    }
+
+    /// Returns the tag, without quotes.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Returns whether the tag is weak, ie. prefixed with `W/`.
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// Compares the tags with the strong comparison of RFC 9110: both
+    /// must be strong, and have the same tag.
+    pub fn strong_eq(&self, other: &ETag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Compares the tags with the weak comparison of RFC 9110: they
+    /// must have the same tag, whether they are weak or not.
+    pub fn weak_eq(&self, other: &ETag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl fmt::Display for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.weak {
+            write!(f, "W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+/// How long to wait before retrying a request, from the `Retry-After`
+/// header of eg. a `503 Service Unavailable` or `429 Too Many
+/// Requests` response.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RetryAfter {
+    /// A number of seconds to wait.
+    Delay(Duration),
+    /// A time to wait until.
+    Date(SystemTime),
+}
+
+impl RetryAfter {
+    /// Parses a `Retry-After` header value, either a number of seconds
+    /// or an HTTP-date.
+    pub fn parse(value: &str) -> Option<RetryAfter> {
+        let value = value.trim();
+        if !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) {
+            let seconds = value.parse().unwrap_or(u64::MAX);
+            Some(RetryAfter::Delay(Duration::from_secs(seconds)))
+        } else {
+            crate::date::parse(value).map(RetryAfter::Date)
+        }
+    }
+
+    /// Returns how long to wait from now. Zero if the date has
+    /// already passed.
+    pub fn delay(&self) -> Duration {
+        match self {
+            RetryAfter::Delay(delay) => *delay,
+            RetryAfter::Date(date) => date
+                .duration_since(SystemTime::now())
+                .unwrap_or(Duration::ZERO),
+        }
+    }
+}
+
+/// The byte range of a partial response, from the `Content-Range`
+/// header, eg. `bytes 0-499/1234`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ContentRange {
+    /// The first and last byte of the range, inclusive. `None` if the
+    /// range could not be satisfied, in a
+    /// `416 Range Not Satisfiable` response.
+    pub range: Option<(u64, u64)>,
+    /// The length of the whole representation, or `None` if the
+    /// server does not know it.
+    pub complete_length: Option<u64>,
+}
+
+impl ContentRange {
+    /// Parses a `Content-Range` header value in bytes. Returns `None`
+    /// for other units, and for ranges that are empty or end past the
+    /// complete length.
+    pub fn parse(value: &str) -> Option<ContentRange> {
+        let (unit, rest) = value.trim().split_once(' ')?;
+        if !unit.eq_ignore_ascii_case("bytes") {
+            return None;
+        }
+        let (range, complete_length) = rest.trim().split_once('/')?;
+        let complete_length = match complete_length {
+            "*" => None,
+            length => Some(parse_u64(length)?),
+        };
+        let range = match range {
+            "*" if complete_length.is_some() => None,
+            range => {
+                let (first, last) = range.split_once('-')?;
+                let (first, last) = (parse_u64(first)?, parse_u64(last)?);
+                if first > last || complete_length.is_some_and(|length| last >= length) {
+                    return None;
+                }
+                Some((first, last))
+            }
+        };
+        Some(ContentRange {
+            range,
+            complete_length,
+        })
+    }
+}
+
+fn parse_u64(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// Returns whether `value` is a token, ie. can be a name, or a value
+/// without quotes.
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
+
+/// Splits `value` on `separator`, except inside quoted strings.
+fn split_outside_quotes(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Removes the quotes and escapes of a quoted string, or returns
+/// `value` as it is if it is not quoted. Returns `None` if the quotes
+/// are not closed.
+fn unquote(value: &str) -> Option<String> {
+    let Some(quoted) = value.strip_prefix('"') else {
+        return Some(value.to_string());
+    };
+    let mut unquoted = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.push(chars.next()?),
+            '"' => return chars.as_str().is_empty().then_some(unquoted),
+            c => unquoted.push(c),
+        }
+    }
+    None
+}
+
+/// Quotes `value`, if it is not a token.
+fn quote(value: &str) -> Cow<'_, str> {
+    if is_token(value) {
+        Cow::Borrowed(value)
+    } else {
+        let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+        Cow::Owned(format!("\"{}\"", escaped))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheControl, ContentRange, ContentType, ETag, RetryAfter};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn parses_content_types() {
+        let content_type =
+            ContentType::parse("Text/HTML; Charset=\"utf-8\"; boundary=\"a;b\"").unwrap();
+        assert_eq!(content_type.essence(), "text/html");
+        assert_eq!(content_type.main_type(), "text");
+        assert_eq!(content_type.subtype(), "html");
+        assert_eq!(content_type.charset(), Some("utf-8"));
+        assert_eq!(content_type.param("BOUNDARY"), Some("a;b"));
+        assert_eq!(
+            content_type.to_string(),
+            "text/html; charset=utf-8; boundary=\"a;b\""
+        );
+
+        assert!(ContentType::parse("text/plain;;").is_some());
+        for value in ["", "text", "text/", "text/plain; charset=\"utf-8"] {
+            assert_eq!(ContentType::parse(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn parses_cache_control() {
+        let cache_control =
+            CacheControl::parse("Max-Age=3600, private=\"Set-Cookie, X-Id\", no-cache").unwrap();
+        assert_eq!(cache_control.max_age(), Some(Duration::from_secs(3600)));
+        assert_eq!(cache_control.get("private"), Some("Set-Cookie, X-Id"));
+        assert!(cache_control.no_cache() && cache_control.is_private());
+        assert!(!cache_control.no_store());
+        assert_eq!(cache_control.s_maxage(), None);
+
+        let cache_control = CacheControl::new()
+            .with_max_age(Duration::from_secs(60))
+            .with_directive("No-Transform");
+        assert_eq!(cache_control.to_string(), "max-age=60, no-transform");
+        assert_eq!(CacheControl::parse("max-age=\"60"), None);
+    }
+
+    #[test]
+    fn compares_etags() {
+        let strong = ETag::parse("\"xyzzy\"").unwrap();
+        let weak = ETag::parse("W/\"xyzzy\"").unwrap();
+        assert!(!strong.is_weak() && weak.is_weak());
+        assert_eq!(weak.tag(), "xyzzy");
+        assert!(strong.strong_eq(&ETag::strong("xyzzy")));
+        assert!(!strong.strong_eq(&weak) && strong.weak_eq(&weak));
+        assert_eq!(weak.to_string(), "W/\"xyzzy\"");
+        assert_eq!(ETag::parse("xyzzy"), None);
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(
+            RetryAfter::parse("120"),
+            Some(RetryAfter::Delay(Duration::from_secs(120)))
+        );
+        let date = RetryAfter::parse("Fri, 31 Dec 1999 23:59:59 GMT").unwrap();
+        assert_eq!(
+            date,
+            RetryAfter::Date(UNIX_EPOCH + Duration::from_secs(946_684_799))
+        );
+        assert_eq!(date.delay(), Duration::ZERO);
+        assert_eq!(RetryAfter::parse("soon"), None);
+    }
+
+    #[test]
+    fn parses_content_ranges() {
+        let range = |value| ContentRange::parse(value).map(|r| (r.range, r.complete_length));
+        assert_eq!(
+            range("bytes 0-499/1234"),
+            Some((Some((0, 499)), Some(1234)))
+        );
+        assert_eq!(range("bytes 500-999/*"), Some((Some((500, 999)), None)));
+        assert_eq!(range("bytes */1234"), Some((None, Some(1234))));
+        for value in ["bytes */*", "bytes 5-1/10", "bytes 0-10/10", "items 0-1/2"] {
+            assert_eq!(range(value), None, "{:?}", value);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 extern crate tiny_http;
 extern crate tinyget;
-use self::tiny_http::{Header, Method, Response, Server};
+use self::tiny_http::{Header, Method, Response, Server, StatusCode};
+use std::io::Read;
 use std::sync::Arc;
 use std::sync::Once;
 use std::thread;
@@ -52,6 +53,13 @@ pub fn setup() {
                         request.respond(response).ok();
                     }
 
+                    Method::Post if url == "/redirect-post" => {
+                        let response = Response::empty(307).with_header(
+                            Header::from_bytes(&b"Location"[..], &b"/post"[..]).unwrap(),
+                        );
+                        request.respond(response).ok();
+                    }
+
                     Method::Get if url == "/slow_a" => {
                         thread::sleep(Duration::from_secs(2));
                         let response = Response::from_string(format!("j: {}", content));
@@ -104,6 +112,36 @@ pub fn setup() {
                         request.respond(response).ok();
                     }
 
+                    // Large responses for the benchmarks, eg. /bytes/1024.
+                    Method::Get if url.starts_with("/bytes/") => {
+                        let length = url["/bytes/".len()..].parse().unwrap_or(0);
+                        let body = std::io::repeat(b'a').take(length as u64);
+                        let response =
+                            Response::new(StatusCode(200), Vec::new(), body, Some(length), None)
+                                .with_chunked_threshold(usize::MAX);
+                        request.respond(response).ok();
+                    }
+
+                    Method::Get if url.starts_with("/chunked/") => {
+                        let length = url["/chunked/".len()..].parse().unwrap_or(0);
+                        let body = std::io::repeat(b'a').take(length);
+                        let response = Response::new(StatusCode(200), Vec::new(), body, None, None);
+                        request.respond(response).ok();
+                    }
+
+                    Method::Get if url.starts_with("/headers/") => {
+                        let count = url["/headers/".len()..].parse().unwrap_or(0);
+                        let mut response = Response::empty(200);
+                        for i in 0..count {
+                            let name = format!("X-Header-{}", i);
+                            let value = format!("value {}", i);
+                            response.add_header(
+                                Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap(),
+                            );
+                        }
+                        request.respond(response).ok();
+                    }
+
                     Method::Get if url == "/relativeredirect" => {
                         let response = Response::empty(303)
                             .with_header(Header::from_bytes(&b"Location"[..], &b"/a"[..]).unwrap());
@@ -143,7 +181,7 @@ pub fn get_body(request: Result<tinyget::Response, tinyget::Error>) -> String {
 
 pub fn get_status_code(request: Result<tinyget::Response, tinyget::Error>) -> i32 {
     match request {
-        Ok(response) => response.status_code,
+        Ok(response) => i32::from(response.status_code),
         Err(err) => {
             println!("\n[ERROR]: {}\n", err);
             -1
@@ -170,6 +208,8 @@ fn echo_query_params(url: &str) -> String {
 }
 
 fn decode_query_component(value: &str) -> String {
+    let value = value.replace('+', " ");
+    let value = value.as_str();
     urlencoding::decode(value)
         .map(|value| value.into_owned())
         .unwrap_or_else(|_| value.to_string())
@@ -178,3 +218,64 @@ fn decode_query_component(value: &str) -> String {
 fn escape_json(value: &str) -> String {
     value.replace('\\', "\\\\").replace('"', "\\\"")
 }
+
+/// Serves `response` verbatim to the first connection made to the
+/// returned url, after reading the request. Useful for testing wire
+/// formats tiny_http can't produce.
+#[allow(dead_code)]
+pub fn serve_raw(response: &'static [u8]) -> String {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                break;
+            }
+            let lowercase = line.to_ascii_lowercase();
+            if let Some(length) = lowercase.strip_prefix("content-length:") {
+                content_length = length.trim().parse().unwrap_or(0);
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).ok();
+        reader.get_mut().write_all(response).ok();
+    });
+    format!("http://127.0.0.1:{}/", port)
+}
+
+/// Calls `handler` with each of the first `connections` connections
+/// made to the returned url, after reading the head of the request,
+/// which is passed along. Useful for testing exchanges tiny_http
+/// can't produce.
+#[allow(dead_code)]
+pub fn serve_with<F>(connections: usize, handler: F) -> String
+where
+    F: Fn(String, &mut std::io::BufReader<std::net::TcpStream>) + Send + 'static,
+{
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for _ in 0..connections {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            while !head.ends_with("\r\n\r\n") {
+                if reader.read_line(&mut head).unwrap_or(0) == 0 {
+                    break;
+                }
+            }
+            handler(head, &mut reader);
+        }
+    });
+    format!("http://127.0.0.1:{}/", port)
+}
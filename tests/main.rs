@@ -2,6 +2,7 @@ extern crate tinyget;
 mod common;
 
 use self::common::*;
+use std::time::Duration;
 
 #[test]
 // Test based on issue #23: https://github.com/neonmoe/minreq/issues/23
@@ -85,6 +86,142 @@ fn test_post() {
     assert_eq!(body, "method: POST\nbody: hello");
 }
 
+#[test]
+fn test_post_body_reader() {
+    setup();
+    let request = tinyget::post(url("/post")).with_body_reader(&b"hello, world"[..], Some(5));
+    assert_eq!(get_body(request.send()), "method: POST\nbody: hello");
+
+    let request = tinyget::post(url("/post")).with_body_reader(&b"hello, world"[..], None);
+    assert_eq!(get_body(request.send()), "method: POST\nbody: hello, world");
+}
+
+#[test]
+fn test_chunked_body_reader_wire_format() {
+    use std::io::{BufRead, Write};
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let url = serve_with(1, move |head, reader| {
+        let mut body = String::new();
+        while !body.ends_with("\r\n\r\n") {
+            reader.read_line(&mut body).unwrap();
+        }
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        tx.send((head, body)).unwrap();
+    });
+
+    tinyget::post(url)
+        .with_body_reader(&b"Wikipedia"[..], None)
+        .with_trailer("X-Checksum", "42")
+        .send()
+        .unwrap();
+    let (head, body) = rx.recv().unwrap();
+    assert!(head.contains("\r\nTransfer-Encoding: chunked\r\n"));
+    assert!(head.contains("\r\nTrailer: X-Checksum\r\n"));
+    assert!(!head.contains("Content-Length"));
+    assert_eq!(body, "9\r\nWikipedia\r\n0\r\nX-Checksum: 42\r\n\r\n");
+}
+
+#[test]
+fn test_post_body_file() {
+    use std::io::Write;
+
+    setup();
+    let path = std::env::temp_dir().join(format!("tinyget-post-body-file-{}", std::process::id()));
+    std::fs::File::create(&path)
+        .unwrap()
+        .write_all(b"hello from a file")
+        .unwrap();
+    let file = std::fs::File::open(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    // The redirection sends the file a second time.
+    let request = tinyget::post(url("/redirect-post")).with_body_file(file);
+    assert_eq!(
+        get_body(request.send()),
+        "method: POST\nbody: hello from a file"
+    );
+}
+
+#[test]
+fn test_post_multipart() {
+    use std::io::Write;
+
+    setup();
+    let file_name = format!("tinyget-multipart-{}.txt", std::process::id());
+    let path = std::env::temp_dir().join(&file_name);
+    std::fs::File::create(&path)
+        .unwrap()
+        .write_all(b"file contents")
+        .unwrap();
+    let form = tinyget::Multipart::new()
+        .text("name", "value")
+        .file("upload", &path)
+        .unwrap();
+    std::fs::remove_file(&path).ok();
+    let boundary = form.boundary().to_string();
+
+    let body = get_body(tinyget::post(url("/post")).with_multipart(form).send());
+    let expected = format!(
+        "method: POST\nbody: --{0}\r\n\
+         Content-Disposition: form-data; name=\"name\"\r\n\r\nvalue\r\n--{0}\r\n\
+         Content-Disposition: form-data; name=\"upload\"; filename=\"{1}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\nfile contents\r\n--{0}--\r\n",
+        boundary, file_name
+    );
+    assert_eq!(body, expected);
+
+    let form = tinyget::Multipart::new().part("stream", tinyget::Part::reader(&b"abc"[..], None));
+    let body = get_body(tinyget::post(url("/post")).with_multipart(form).send());
+    assert!(body.contains("\r\n\r\nabc\r\n"));
+}
+
+#[test]
+fn test_post_form() {
+    setup();
+    let request = tinyget::post(url("/post")).with_form([("a", "1 2"), ("b", "&")]);
+    assert_eq!(get_body(request.send()), "method: POST\nbody: a=1+2&b=%26");
+}
+
+#[test]
+fn test_response_form() {
+    let url = serve_raw(
+        b"HTTP/1.1 200 OK\r\nContent-Type: application/x-www-form-urlencoded\r\n\
+          Content-Length: 59\r\n\r\naccess_token=a%2Bb&token_type=bearer&scope=x+y&scope=z&flag",
+    );
+    let form = tinyget::get(url).send().unwrap().form().unwrap();
+    let form: Vec<(&str, &str)> = form.iter().map(|(k, v)| (&k[..], &v[..])).collect();
+    assert_eq!(
+        form,
+        [
+            ("access_token", "a+b"),
+            ("token_type", "bearer"),
+            ("scope", "x y"),
+            ("scope", "z"),
+            ("flag", "")
+        ]
+    );
+}
+
+#[test]
+fn test_body_reader_is_not_resent_on_redirect() {
+    setup();
+    let request = tinyget::post(url("/redirect-post")).with_body_reader(&b"hello"[..], Some(5));
+    assert!(matches!(
+        request.send(),
+        Err(tinyget::Error::BodyAlreadySent)
+    ));
+    let body = get_body(
+        tinyget::post(url("/redirect-post"))
+            .with_body("hello")
+            .send(),
+    );
+    assert_eq!(body, "method: POST\nbody: hello");
+}
+
 #[test]
 fn test_redirect_get() {
     setup();
@@ -95,15 +232,16 @@ fn test_redirect_get() {
 #[test]
 fn test_redirect_with_fragment() {
     setup();
+    // The fragment is kept over the redirection, but never sent.
     let body = get_body(tinyget::get(url("/redirect#foo")).send());
-    assert_eq!(body, "j: Qfoo");
+    assert_eq!(body, "j: Q");
 }
 
 #[test]
 fn test_redirect_with_overridden_fragment() {
     setup();
     let body = get_body(tinyget::get(url("/redirect-baz#foo")).send());
-    assert_eq!(body, "j: Qbaz");
+    assert_eq!(body, "j: Q");
 }
 
 #[test]
@@ -119,3 +257,840 @@ fn test_relative_redirect_get() {
     let body = get_body(tinyget::get(url("/relativeredirect")).send());
     assert_eq!(body, "j: Q");
 }
+
+#[test]
+fn test_chunked_trailers() {
+    let url = serve_raw(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: Expires\r\n\r\n\
+          4\r\nWiki\r\n5\r\npedia\r\n0\r\nExpires: never\r\n\r\n",
+    );
+    let response = tinyget::get(url).send().unwrap();
+    assert_eq!(response.as_str().unwrap(), "Wikipedia");
+    assert_eq!(
+        response.headers.get("transfer-encoding").unwrap(),
+        "chunked"
+    );
+    assert_eq!(response.headers.get("content-length"), None);
+    assert_eq!(response.headers.get("expires"), None);
+    assert_eq!(response.trailers.get("expires").unwrap(), "never");
+}
+
+#[test]
+fn test_lazy_trailers_after_drain() {
+    let url = serve_raw(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+          3\r\nabc\r\n0\r\nX-Checksum: 42\r\n\r\n",
+    );
+    let mut response = tinyget::get(url).send_lazy().unwrap();
+    assert!(response.trailers.is_empty());
+    for byte in &mut response {
+        byte.unwrap();
+    }
+    assert_eq!(response.body_len(), 3);
+    assert_eq!(response.trailers.get("x-checksum").unwrap(), "42");
+}
+
+#[test]
+fn test_max_line_length() {
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nX-Long: aaaaaaaaaaaaaaaaaaaaaaaa\r\n\r\n");
+    let result = tinyget::get(url).with_max_line_length(16).send();
+    assert!(matches!(result, Err(tinyget::Error::LineTooLong)));
+
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+    let response = tinyget::get(url)
+        .with_max_line_length(usize::MAX)
+        .send()
+        .unwrap();
+    assert_eq!(response.as_bytes(), b"ok");
+}
+
+#[test]
+fn test_max_header_count() {
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nA: 1\r\nB: 2\r\nC: 3\r\nContent-Length: 0\r\n\r\n");
+    let result = tinyget::get(url).with_max_header_count(3).send();
+    assert!(matches!(result, Err(tinyget::Error::TooManyHeaders)));
+}
+
+#[test]
+fn test_max_headers_size() {
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nA: 1234567890\r\nB: 1234567890\r\n\r\n");
+    let result = tinyget::get(url).with_max_headers_size(20).send();
+    assert!(matches!(result, Err(tinyget::Error::HeadersTooLarge)));
+}
+
+#[test]
+fn test_max_body_size() {
+    setup();
+    let result = tinyget::get(url("/a")).with_max_body_size(3).send();
+    assert!(matches!(result, Err(tinyget::Error::BodyTooLarge)));
+    let body = get_body(tinyget::get(url("/a")).with_max_body_size(4).send());
+    assert_eq!(body, "j: Q");
+}
+
+#[test]
+fn test_strict_rejects_bare_line_feed() {
+    let url = serve_raw(b"HTTP/1.1 200 OK\nContent-Length: 2\n\nok");
+    let result = tinyget::get(url)
+        .with_parse_mode(tinyget::ParseMode::Strict)
+        .send();
+    assert!(matches!(result, Err(tinyget::Error::BareLineFeed)));
+}
+
+#[test]
+fn test_strict_rejects_obs_fold() {
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nX-Folded: a\r\n b\r\nContent-Length: 0\r\n\r\n");
+    let result = tinyget::get(url)
+        .with_parse_mode(tinyget::ParseMode::Strict)
+        .send();
+    assert!(matches!(result, Err(tinyget::Error::ObsoleteLineFolding)));
+}
+
+#[test]
+fn test_strict_rejects_latin1() {
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nX-Name: Andr\xe9\r\nContent-Length: 0\r\n\r\n");
+    let result = tinyget::get(url)
+        .with_parse_mode(tinyget::ParseMode::Strict)
+        .send();
+    assert!(matches!(result, Err(tinyget::Error::InvalidUtf8InResponse)));
+}
+
+#[test]
+fn test_strict_rejects_header_without_colon() {
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nnot a header\r\nContent-Length: 0\r\n\r\n");
+    let result = tinyget::get(url)
+        .with_parse_mode(tinyget::ParseMode::Strict)
+        .send();
+    assert!(matches!(result, Err(tinyget::Error::MalformedHeader)));
+}
+
+#[test]
+fn test_strict_rejects_whitespace_before_colon() {
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nContent-Length : 0\r\n\r\n");
+    let result = tinyget::get(url)
+        .with_parse_mode(tinyget::ParseMode::Strict)
+        .send();
+    assert!(matches!(result, Err(tinyget::Error::MalformedHeader)));
+}
+
+#[test]
+fn test_lenient_parsing() {
+    let url = serve_raw(
+        b"HTTP/1.1 200 OK\nX-Folded: a\r\n \t b\nX-Name: Andr\xe9\r\nnot a header\r\n\
+          Content-Length: 2\n\nok",
+    );
+    let response = tinyget::get(url)
+        .with_parse_mode(tinyget::ParseMode::Lenient)
+        .send()
+        .unwrap();
+    assert_eq!(response.headers.get("x-folded").unwrap(), "a b");
+    assert_eq!(response.headers.get("x-name").unwrap(), "André");
+    assert_eq!(response.as_str().unwrap(), "ok");
+}
+
+#[test]
+fn test_lenient_parsing_by_default() {
+    let url = serve_raw(b"HTTP/1.1 200 OK\nnot a header\nContent-Length \t: 2\n\nok");
+    let response = tinyget::get(url).send().unwrap();
+    assert_eq!(response.headers.get("content-length").unwrap(), "2");
+    assert_eq!(response.as_str().unwrap(), "ok");
+}
+
+#[test]
+fn test_repeated_headers() {
+    let url = serve_raw(
+        b"HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\nVia: 1.1 proxy\r\nset-cookie: b=2\r\n\
+          Content-Length: 0\r\n\r\n",
+    );
+    let response = tinyget::get(url).send().unwrap();
+    let cookies: Vec<&str> = response.headers.get_all("Set-Cookie").collect();
+    assert_eq!(cookies, ["a=1", "b=2"]);
+    let names: Vec<&str> = response.headers.iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["Set-Cookie", "Via", "set-cookie", "Content-Length"]);
+}
+
+#[test]
+fn test_template() {
+    setup();
+    let request = tinyget::Request::from_template(&url("/{path}"), [("path", "a")]).unwrap();
+    assert_eq!(get_body(request.send()), "j: Q");
+
+    let request = tinyget::Request::from_template(&url("/query?q={q}"), [("q", "a&b=c")]).unwrap();
+    assert_eq!(get_body(request.send()), "\"q\": \"a&b=c\"\n");
+}
+
+#[test]
+fn test_error_for_status() {
+    setup();
+    let response = tinyget::get(url("/a")).send().unwrap();
+    assert!(response.status_code.is_success());
+    assert!(response.error_for_status().is_ok());
+
+    let response = tinyget::get(url("/not_found")).send().unwrap();
+    assert_eq!(response.status_code, tinyget::StatusCode::NOT_FOUND);
+    match response.error_for_status() {
+        Err(tinyget::Error::HttpStatus {
+            status_code, body, ..
+        }) => {
+            assert!(status_code.is_client_error());
+            assert_eq!(body, b"Not Found");
+        }
+        other => panic!("expected an http status error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_version_and_addresses() {
+    setup();
+    let response = tinyget::get(url("/a")).send().unwrap();
+    assert_eq!(response.version(), tinyget::HttpVersion::Http11);
+    assert_eq!(response.remote_addr().unwrap().port(), 35562);
+    assert!(response.local_addr().unwrap().ip().is_loopback());
+
+    let url = serve_raw(b"HTTP/1.0 200 OK\r\n\r\nold");
+    let response = tinyget::get(url).send().unwrap();
+    assert_eq!(response.version(), tinyget::HttpVersion::Http10);
+    assert_eq!(response.as_str().unwrap(), "old");
+}
+
+#[test]
+fn test_rejects_other_protocols() {
+    let url = serve_raw(b"HTTP/2 200 OK\r\n\r\n");
+    let result = tinyget::get(url).send();
+    assert!(matches!(
+        result,
+        Err(tinyget::Error::UnsupportedHttpVersion)
+    ));
+}
+
+#[test]
+fn test_timings() {
+    setup();
+    let response = tinyget::get(url("/redirect")).send().unwrap();
+    let timings = response.timings();
+    assert_eq!(timings.tls_handshake, None);
+    assert!(timings.download.is_some());
+    assert_eq!(timings.redirects.len(), 1);
+    assert_eq!(timings.redirects[0].download, None);
+
+    let mut response = tinyget::get(url("/a")).send_lazy().unwrap();
+    assert_eq!(response.timings().download, None);
+    for byte in &mut response {
+        byte.unwrap();
+    }
+    assert!(response.timings().download.is_some());
+}
+
+#[test]
+fn test_conflicting_content_lengths() {
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nContent-Length: 2\r\n\r\nok");
+    assert_eq!(tinyget::get(url).send().unwrap().as_bytes(), b"ok");
+
+    for head in [
+        &b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nContent-Length: 7\r\n\r\nok"[..],
+        &b"HTTP/1.1 200 OK\r\nContent-Length: 2, 7\r\n\r\nok"[..],
+    ] {
+        let url = serve_raw(head);
+        assert!(matches!(
+            tinyget::get(url).send(),
+            Err(tinyget::Error::MalformedContentLength)
+        ));
+    }
+}
+
+#[test]
+fn test_chunked_is_the_last_transfer_coding() {
+    let url = serve_raw(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: identity, Chunked\r\n\r\n\
+          2\r\nok\r\n0\r\n\r\n",
+    );
+    assert_eq!(tinyget::get(url).send().unwrap().as_bytes(), b"ok");
+
+    let url = serve_raw(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTransfer-Encoding: identity\r\n\
+          Content-Length: 1\r\n\r\nok",
+    );
+    assert_eq!(tinyget::get(url).send().unwrap().as_bytes(), b"ok");
+}
+
+#[test]
+fn test_huge_content_length_is_not_preallocated() {
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 1000000000000\r\n\r\nok");
+    let result = tinyget::get(url).send();
+    assert!(matches!(result, Err(tinyget::Error::IoError(err))
+        if err.kind() == std::io::ErrorKind::UnexpectedEof));
+}
+
+#[test]
+fn test_body_cut_short() {
+    for response in [
+        &b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nok"[..],
+        &b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nok"[..],
+    ] {
+        let url = serve_raw(response);
+        let result = tinyget::get(url).send();
+        assert!(matches!(result, Err(tinyget::Error::IoError(err))
+            if err.kind() == std::io::ErrorKind::UnexpectedEof));
+    }
+
+    // Without a length, the end of the connection ends the body.
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\n\r\nok");
+    assert_eq!(tinyget::get(url).send().unwrap().as_bytes(), b"ok");
+}
+
+#[test]
+fn test_read_chunked_body() {
+    let url = serve_raw(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+          4;name=value\r\nWiki\r\n5\r\npedia\r\n0\r\nX-Checksum: 42\r\n\r\n",
+    );
+    let mut response = tinyget::get(url).send_lazy().unwrap();
+    let mut body = Vec::new();
+    std::io::copy(&mut response, &mut body).unwrap();
+    assert_eq!(body, b"Wikipedia");
+    assert_eq!(response.body_len(), 9);
+    assert_eq!(response.trailers.get("x-checksum").unwrap(), "42");
+}
+
+#[test]
+fn test_read_lines() {
+    use std::io::BufRead;
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\nfirst\nsecond\nignored");
+    let response = tinyget::get(url).send_lazy().unwrap();
+    let lines: Vec<String> = response.lines().map(Result::unwrap).collect();
+    assert_eq!(lines, ["first", "second"]);
+}
+
+#[test]
+fn test_read_errors_convert_back() {
+    use std::io::Read;
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 8\r\n\r\n12345678");
+    let mut response = tinyget::get(url).with_max_body_size(4).send_lazy().unwrap();
+    let err = response.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(matches!(
+        tinyget::Error::from(err),
+        tinyget::Error::BodyTooLarge
+    ));
+}
+
+#[test]
+fn test_header_longer_than_read_buffer() {
+    let value = "v".repeat(40_000);
+    let raw = format!(
+        "HTTP/1.1 200 OK\r\nX-Long: {}\r\nContent-Length: 2\r\n\r\nok",
+        value
+    );
+    let url = serve_raw(Box::leak(raw.into_bytes().into_boxed_slice()));
+    let response = tinyget::get(url).send().unwrap();
+    assert_eq!(response.headers.get("x-long"), Some(value.as_str()));
+    assert_eq!(response.as_str().unwrap(), "ok");
+}
+
+/// Reads a body of `length` bytes, and responds with it.
+fn echo_body(reader: &mut std::io::BufReader<std::net::TcpStream>, length: usize) {
+    use std::io::{Read, Write};
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    let mut response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", length);
+    response += std::str::from_utf8(&body).unwrap();
+    reader.get_mut().write_all(response.as_bytes()).unwrap();
+}
+
+#[test]
+fn test_expect_continue() {
+    use std::io::Write;
+
+    let url = serve_with(1, |head, reader| {
+        assert!(head.contains("\r\nExpect: 100-continue\r\n"));
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .unwrap();
+        echo_body(reader, 5);
+    });
+    let request = tinyget::post(url)
+        .with_body("hello")
+        .with_expect_continue(Duration::from_secs(5));
+    let response = request.send().unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.as_str().unwrap(), "hello");
+}
+
+#[test]
+fn test_expect_continue_rejected_early() {
+    use std::io::{Read, Write};
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let url = serve_with(1, move |_, reader| {
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).ok();
+        tx.send(rest).unwrap();
+    });
+    let request = tinyget::post(url)
+        .with_body_reader(&b"secret"[..], Some(6))
+        .with_expect_continue(Duration::from_secs(5));
+    let response = request.send().unwrap();
+    assert_eq!(response.status_code, 401);
+    assert!(rx.recv().unwrap().is_empty());
+}
+
+#[test]
+fn test_expect_continue_without_answer() {
+    let url = serve_with(1, |_, reader| echo_body(reader, 5));
+    let request = tinyget::post(url)
+        .with_body("hello")
+        .with_expect_continue(Duration::from_millis(500));
+    assert_eq!(request.send().unwrap().as_str().unwrap(), "hello");
+}
+
+#[test]
+fn test_expect_continue_expectation_failed() {
+    use std::io::Write;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let retried = AtomicBool::new(false);
+    let url = serve_with(2, move |head, reader| {
+        if !retried.swap(true, Ordering::SeqCst) {
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 417 Expectation Failed\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+        } else {
+            assert!(!head.contains("Expect"));
+            echo_body(reader, 5);
+        }
+    });
+    let request = tinyget::post(url)
+        .with_header("Expect", "100-continue")
+        .with_body("hello")
+        .with_expect_continue(Duration::from_secs(5));
+    let response = request.send().unwrap();
+    assert_eq!(response.as_str().unwrap(), "hello");
+    assert!(response.timings().expectation_failed.is_some());
+    assert!(response.timings().redirects.is_empty());
+}
+
+#[test]
+fn test_skips_interim_responses() {
+    let url = serve_raw(
+        b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>\r\n\r\n\
+          HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok",
+    );
+    let response = tinyget::get(url).send().unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.headers.get("link"), None);
+    assert_eq!(response.as_str().unwrap(), "ok");
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn test_json() {
+    use std::io::{Read, Write};
+
+    let url = serve_with(1, |head, reader| {
+        assert!(head.contains("\r\nContent-Type: application/json\r\n"));
+        assert!(head.contains("\r\nContent-Length: 18\r\n"));
+        let mut body = [0; 18];
+        reader.read_exact(&mut body).unwrap();
+        assert_eq!(&body, br#"{"name":"tinyget"}"#);
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 18\r\n\r\n{\"id\":1,\"ok\":true}")
+            .unwrap();
+    });
+    let body = serde_json::json!({ "name": "tinyget" });
+    let response = tinyget::post(url).with_json(&body).unwrap().send().unwrap();
+    let value: serde_json::Value = response.json().unwrap();
+    assert_eq!(value, serde_json::json!({ "id": 1, "ok": true }));
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn test_lazy_json() {
+    let url = serve_raw(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+          5\r\n[1, 2\r\n4\r\n, 3]\r\n0\r\n\r\n",
+    );
+    let response = tinyget::get(url).send_lazy().unwrap();
+    let value: Vec<u32> = response.json().unwrap();
+    assert_eq!(value, [1, 2, 3]);
+
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\n[1, x]");
+    let result: Result<Vec<u32>, _> = tinyget::get(url).send_lazy().unwrap().json();
+    assert!(matches!(
+        result,
+        Err(tinyget::Error::Json {
+            offset: Some(5),
+            ..
+        })
+    ));
+
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\n[1, 2, 3]");
+    let result: Result<Vec<u32>, _> = tinyget::get(url)
+        .with_max_body_size(4)
+        .send_lazy()
+        .unwrap()
+        .json();
+    assert!(matches!(result, Err(tinyget::Error::BodyTooLarge)));
+}
+
+#[test]
+#[cfg(feature = "cbor")]
+fn test_cbor() {
+    use std::io::{Read, Write};
+
+    let url = serve_with(1, |head, reader| {
+        assert!(head.contains("\r\nContent-Type: application/cbor\r\n"));
+        assert!(head.contains("\r\nContent-Length: 4\r\n"));
+        let mut body = [0; 4];
+        reader.read_exact(&mut body).unwrap();
+        assert_eq!(body, [0x83, 0x01, 0x02, 0x03]);
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/cbor\r\nContent-Length: 2\r\n\r\n\x81\x04")
+            .unwrap();
+    });
+    let response = tinyget::post(url)
+        .with_cbor(&[1, 2, 3])
+        .unwrap()
+        .send()
+        .unwrap();
+    let value: Vec<u32> = response.cbor().unwrap();
+    assert_eq!(value, [4]);
+
+    let url = serve_raw(
+        b"HTTP/1.1 200 OK\r\nContent-Type: application/cbor\r\nContent-Length: 2\r\n\r\n\x82\x01",
+    );
+    let result: Result<Vec<u32>, _> = tinyget::get(url).send_lazy().unwrap().cbor();
+    assert!(matches!(result, Err(tinyget::Error::Cbor { .. })));
+}
+
+#[test]
+#[cfg(feature = "msgpack")]
+fn test_msgpack() {
+    use std::io::{Read, Write};
+
+    let url = serve_with(1, |head, reader| {
+        assert!(head.contains("\r\nContent-Type: application/msgpack\r\n"));
+        assert!(head.contains("\r\nContent-Length: 4\r\n"));
+        let mut body = [0; 4];
+        reader.read_exact(&mut body).unwrap();
+        assert_eq!(body, [0x93, 0x01, 0x02, 0x03]);
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/x-msgpack\r\nContent-Length: 4\r\n\r\n\xa3abc")
+            .unwrap();
+    });
+    let response = tinyget::post(url)
+        .with_msgpack(&[1, 2, 3])
+        .unwrap()
+        .send()
+        .unwrap();
+    let value: &str = response.msgpack().unwrap();
+    assert_eq!(value, "abc");
+
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nContent-Type: application/vnd.msgpack\r\nTransfer-Encoding: chunked\r\n\r\n1\r\n\x92\r\n2\r\n\x01\x02\r\n0\r\n\r\n");
+    let value: Vec<u32> = tinyget::get(url).send_lazy().unwrap().msgpack().unwrap();
+    assert_eq!(value, [1, 2]);
+}
+
+#[test]
+fn test_conditional_request() {
+    use std::io::Write;
+
+    let url = serve_with(2, |head, reader| {
+        let response: &[u8] = if head.contains("\r\nIf-None-Match: \"v1\"\r\n") {
+            b"HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\n\r\n"
+        } else {
+            b"HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nCache-Control: max-age=60\r\n\
+              Content-Type: text/plain; charset=utf-8\r\nContent-Length: 2\r\n\r\nok"
+        };
+        reader.get_mut().write_all(response).unwrap();
+    });
+
+    let response = tinyget::get(&url).send().unwrap();
+    assert_eq!(response.content_type().unwrap().essence(), "text/plain");
+    assert_eq!(response.content_length(), Some(2));
+    assert_eq!(
+        response.cache_control().unwrap().max_age(),
+        Some(std::time::Duration::from_secs(60))
+    );
+    let etag = response.etag().unwrap();
+    let response = tinyget::get(&url)
+        .with_if_none_match([etag])
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, tinyget::StatusCode::NOT_MODIFIED);
+}
+
+#[test]
+fn test_text() {
+    let url = serve_raw(
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\n\r\n\xff\xfeh\0i\0",
+    );
+    let response = tinyget::get(url).send().unwrap();
+    assert_eq!(response.text().unwrap(), "hi");
+    assert!(response.as_str().is_err());
+
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=windows-1252\r\nContent-Length: 4\r\n\r\ncaf\xe9");
+    let response = tinyget::get(url).send().unwrap();
+    #[cfg(feature = "charset")]
+    assert_eq!(response.text().unwrap(), "caf\u{e9}");
+    #[cfg(not(feature = "charset"))]
+    assert!(matches!(
+        response.text(),
+        Err(tinyget::Error::UnsupportedCharset(label)) if label == "windows-1252"
+    ));
+    assert!(response.text_with_charset("utf-8").is_err());
+}
+
+#[test]
+#[cfg(feature = "charset")]
+fn test_text_with_meta_charset() {
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 37\r\n\r\n<meta charset=\"Shift_JIS\"><p>\x93\xfa\x96\x7b</p>");
+    let response = tinyget::get(url).send().unwrap();
+    assert_eq!(
+        response.text().unwrap(),
+        "<meta charset=\"Shift_JIS\"><p>\u{65e5}\u{672c}</p>"
+    );
+    let overridden = response.text_with_charset("windows-1252").unwrap();
+    assert!(overridden.ends_with("<p>\u{201c}\u{fa}\u{2013}{</p>"));
+}
+
+#[test]
+#[cfg(feature = "msgpack")]
+fn test_unexpected_content_type() {
+    let url = serve_raw(b"HTTP/1.1 502 Bad Gateway\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: 6\r\n\r\n<html>");
+    let result: Result<Vec<u32>, _> = tinyget::get(url).send_lazy().unwrap().msgpack();
+    match result {
+        Err(tinyget::Error::UnexpectedContentType {
+            expected,
+            content_type,
+        }) => {
+            assert_eq!(expected, "application/msgpack");
+            assert_eq!(content_type.as_deref(), Some("text/html; charset=utf-8"));
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    let url = serve_raw(b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\n\x01");
+    let response = tinyget::get(url).send().unwrap();
+    assert!(matches!(
+        response.msgpack::<u32>(),
+        Err(tinyget::Error::UnexpectedContentType {
+            content_type: None,
+            ..
+        })
+    ));
+}
+
+#[cfg(feature = "compression")]
+fn gzip(data: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[cfg(feature = "compression")]
+fn serve_compressed(content_encoding: &'static str, body: Vec<u8>) -> String {
+    use std::io::Write;
+
+    serve_with(1, move |head, reader| {
+        assert!(head.contains("\r\nAccept-Encoding: gzip, deflate\r\n"));
+        let stream = reader.get_mut();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Encoding: {}\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n",
+            content_encoding,
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(&body).unwrap();
+        stream.write_all(b"\r\n0\r\n\r\n").unwrap();
+    })
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn test_gzip_response() {
+    let text = "Hello, World! ".repeat(100);
+    let body = gzip(text.as_bytes());
+    let wire_len = body.len();
+    let response = tinyget::get(serve_compressed("gzip", body)).send().unwrap();
+    assert_eq!(response.as_str().unwrap(), text);
+    assert_eq!(response.wire_len(), wire_len);
+    assert_eq!(response.headers.get("content-encoding"), None);
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn test_lazy_stacked_compression() {
+    use std::io::{Read, Write};
+
+    let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    zlib.write_all(&gzip(b"compressed twice")).unwrap();
+    let body = zlib.finish().unwrap();
+    let wire_len = body.len();
+    let url = serve_compressed("gzip, deflate", body);
+    let mut response = tinyget::get(url).send_lazy().unwrap();
+    let mut text = String::new();
+    response.read_to_string(&mut text).unwrap();
+    assert_eq!(text, "compressed twice");
+    assert_eq!(response.body_len(), text.len());
+    assert_eq!(response.wire_len(), wire_len);
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn test_decompression_disabled() {
+    use std::io::Write;
+
+    let body = gzip(b"left as is");
+    let expected = body.clone();
+    let url = serve_with(1, move |head, reader| {
+        assert!(!head.contains("Accept-Encoding"));
+        let stream = reader.get_mut();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(&body).unwrap();
+    });
+    let response = tinyget::get(url).with_decompression(false).send().unwrap();
+    assert_eq!(response.headers.get("content-encoding"), Some("gzip"));
+    assert_eq!(response.into_bytes(), expected);
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn test_own_accept_encoding() {
+    use std::io::Write;
+
+    let body = gzip(b"left as is");
+    let expected = body.clone();
+    let url = serve_with(1, move |head, reader| {
+        assert!(head.contains("\r\nAccept-Encoding: gzip\r\n"));
+        let stream = reader.get_mut();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(&body).unwrap();
+    });
+    let response = tinyget::get(url)
+        .with_header("Accept-Encoding", "gzip")
+        .send()
+        .unwrap();
+    assert_eq!(response.headers.get("content-encoding"), Some("gzip"));
+    assert_eq!(response.content_length(), Some(expected.len() as u64));
+    assert_eq!(response.into_bytes(), expected);
+}
+
+/// Reads a chunked request body, without any trailers.
+#[cfg(feature = "compression")]
+fn read_chunked(reader: &mut impl std::io::BufRead) -> Vec<u8> {
+    let mut body = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let size = usize::from_str_radix(line.trim_end(), 16).unwrap();
+        let mut chunk = vec![0; size + 2];
+        reader.read_exact(&mut chunk).unwrap();
+        if size == 0 {
+            return body;
+        }
+        body.extend_from_slice(&chunk[..size]);
+    }
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn test_compressed_request_body() {
+    use std::io::{Read, Write};
+
+    let text = "Hello, World! ".repeat(100);
+    let expected = text.clone();
+    let url = serve_with(2, move |head, reader| {
+        assert!(head.contains("\r\nContent-Encoding: gzip\r\n"));
+        let body = if head.contains("\r\nTransfer-Encoding: chunked\r\n") {
+            read_chunked(reader)
+        } else {
+            let length = head
+                .lines()
+                .find_map(|line| line.strip_prefix("Content-Length: "))
+                .unwrap();
+            let mut body = vec![0; length.parse().unwrap()];
+            reader.read_exact(&mut body).unwrap();
+            body
+        };
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(&body[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, expected);
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    });
+
+    tinyget::post(&url)
+        .with_body(text.clone())
+        .with_body_compression(tinyget::Coding::Gzip)
+        .send()
+        .unwrap();
+    tinyget::post(&url)
+        .with_body_reader(std::io::Cursor::new(text), None)
+        .with_body_compression(tinyget::Coding::Gzip)
+        .send()
+        .unwrap();
+}
+
+#[test]
+#[cfg(feature = "zstd")]
+fn test_zstd_request_body() {
+    use std::io::Write;
+
+    let url = serve_with(1, |head, reader| {
+        assert!(head.contains("\r\nContent-Encoding: zstd\r\n"));
+        let body = read_chunked(reader);
+        assert_eq!(zstd::decode_all(&body[..]).unwrap(), b"hello, world");
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    });
+    tinyget::post(url)
+        .with_body_reader(&b"hello, world"[..], None)
+        .with_body_compression(tinyget::Coding::Zstd)
+        .send()
+        .unwrap();
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn test_decompression_bomb() {
+    let body = gzip(&vec![0; 8 * 1024 * 1024]);
+    let result = tinyget::get(serve_compressed("gzip", body.clone())).send();
+    assert!(matches!(
+        result,
+        Err(tinyget::Error::DecompressionRatioTooHigh)
+    ));
+
+    let response = tinyget::get(serve_compressed("gzip", body))
+        .with_max_decompression_ratio(2000)
+        .send()
+        .unwrap();
+    assert_eq!(response.into_bytes().len(), 8 * 1024 * 1024);
+}
@@ -89,3 +89,35 @@ fn test_existing_query_parameters() -> Result<(), Box<dyn std::error::Error>> {
     assert!(body.contains("\"age\": \"25\""));
     Ok(())
 }
+
+#[test]
+fn test_repeated_queries_in_order() -> Result<(), Box<dyn std::error::Error>> {
+    setup();
+    let response = tinyget::get(url("/query?tag=a"))
+        .append_query("tag", "b")
+        .with_queries(vec![("name", "Tiny Get"), ("tag", "c")])
+        .with_query_encoding(tinyget::QueryEncoding::Form)
+        .send()?;
+
+    let body = get_body(Ok(response));
+
+    assert_eq!(
+        body,
+        "\"tag\": \"a\"\n\"tag\": \"b\"\n\"name\": \"Tiny Get\"\n\"tag\": \"c\"\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_replace_existing_query_parameter() -> Result<(), Box<dyn std::error::Error>> {
+    setup();
+    let response = tinyget::get(url("/query?page=1&sort=asc"))
+        .with_query("page", "2")
+        .remove_query("sort")
+        .send()?;
+
+    let body = get_body(Ok(response));
+
+    assert_eq!(body, "\"page\": \"2\"\n");
+    Ok(())
+}